@@ -0,0 +1,187 @@
+// Times the searches that lean on collision tests and line clears. Run with
+// `cargo bench`; there is no statistics library, so each case reports the
+// mean of a fixed number of runs.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use cgmath::Vector2;
+use tetris::{
+    bot::{Bot, Weights},
+    engine::{
+        piece::{Kind, Rotation},
+        piece_set::PieceSet,
+        Color, Engine, Matrix,
+    },
+    net,
+};
+
+// A ragged stack with overhangs, so searches find tucks and spins
+const STACK: &str = concat!(
+    "XXXX.XXXXX",
+    "XXX.XXXXXX",
+    "X.XXXXXXXX",
+    "XX.XXXX.XX",
+    "X..XXX..XX",
+    "....XX...X",
+    "........X.",
+);
+
+// Four full rows among the rest, so clearing shifts rows more than once
+const CLEARS: &str = concat!(
+    "XXXXXXXXXX",
+    "XXXX.XXXXX",
+    "XXXXXXXXXX",
+    "XXX.XXXXXX",
+    "XXXXXXXXXX",
+    "X.XXXXXXXX",
+    "XXXXXXXXXX",
+    "....XX...X",
+    "........X.",
+);
+
+const WIDTH: usize = Matrix::WIDTH;
+const HEIGHT: usize = Matrix::HEIGHT;
+
+// The matrix as it was before it kept rows as bitmasks, a colour or None
+// for each cell, for the cases that compare the two
+#[derive(Clone, PartialEq, Debug)]
+struct Cells([Option<Color>; WIDTH * HEIGHT]);
+
+impl Cells {
+    fn new(matrix: &Matrix) -> Self {
+        let mut cells = [None; WIDTH * HEIGHT];
+        for (coord, cell) in matrix.cells() {
+            cells[coord.y * WIDTH + coord.x] = cell;
+        }
+        Self(cells)
+    }
+
+    // A cell at a time: off the sides or the bottom, or on a filled cell
+    fn is_clipping(&self, kind: Kind, rotation: Rotation, position: Vector2<isize>) -> bool {
+        let corner = match rotation {
+            Rotation::N => Vector2::new(0, 0),
+            Rotation::E => Vector2::new(0, 1),
+            Rotation::S => Vector2::new(1, 1),
+            Rotation::W => Vector2::new(1, 0),
+        };
+        kind.cells().iter().any(|&cell| {
+            let cell = cell * rotation + corner * (kind.size() - 1) + position;
+            if cell.x < 0 || cell.x >= WIDTH as isize || cell.y < 0 {
+                return true;
+            }
+            let (x, y) = (cell.x as usize, cell.y as usize);
+            y < HEIGHT && self.0[y * WIDTH + x].is_some()
+        })
+    }
+
+    fn clear_full_lines(&mut self) {
+        let full: Vec<_> = (0..HEIGHT)
+            .filter(|&y| {
+                self.0[y * WIDTH..(y + 1) * WIDTH]
+                    .iter()
+                    .all(Option::is_some)
+            })
+            .collect();
+        for &line in full.iter().rev() {
+            self.0.copy_within((line + 1) * WIDTH.., line * WIDTH);
+            self.0[(HEIGHT - 1) * WIDTH..].fill(None);
+        }
+    }
+}
+
+fn bench(name: &str, runs: u32, mut run: impl FnMut()) {
+    run();
+    let start = Instant::now();
+    for _ in 0..runs {
+        run();
+    }
+    let mean = start.elapsed() / runs;
+    println!("{:<24} {:>12?} per run ({} runs)", name, mean, runs);
+}
+
+fn main() {
+    let matrix = net::decode_board(STACK).unwrap();
+    let standard = PieceSet::standard();
+
+    bench("placements, blank", 200, || {
+        for kind in standard.kinds() {
+            let mut engine = Engine::with_matrix(Matrix::blank());
+            engine.DEBUG_test_cursor_location(kind, kind.spawn());
+            black_box(engine.placements());
+        }
+    });
+
+    bench("placements, stacked", 200, || {
+        for kind in standard.kinds() {
+            let mut engine = Engine::with_matrix(matrix.clone());
+            engine.DEBUG_test_cursor_location(kind, kind.spawn());
+            black_box(engine.placements());
+        }
+    });
+
+    // every piece in every rotation, anywhere from past the walls and floor
+    // to above the matrix
+    let mut positions = Vec::new();
+    for kind in standard.kinds() {
+        for rotation in Rotation::ALL {
+            for x in -3..=WIDTH as isize {
+                for y in -3..=HEIGHT as isize {
+                    positions.push((kind, rotation, Vector2::new(x, y)));
+                }
+            }
+        }
+    }
+    let cells = Cells::new(&matrix);
+    for &(kind, rotation, position) in &positions {
+        assert_eq!(
+            matrix.DEBUG_is_clipping(kind, rotation, position),
+            cells.is_clipping(kind, rotation, position),
+            "{:?} {:?} at {:?}",
+            kind,
+            rotation,
+            position
+        );
+    }
+
+    bench("is_clipping, rows", 200, || {
+        for &(kind, rotation, position) in &positions {
+            black_box(matrix.DEBUG_is_clipping(kind, rotation, position));
+        }
+    });
+
+    bench("is_clipping, cells", 200, || {
+        for &(kind, rotation, position) in &positions {
+            black_box(cells.is_clipping(kind, rotation, position));
+        }
+    });
+
+    let clears = net::decode_board(CLEARS).unwrap();
+    let mut cleared = clears.clone();
+    cleared.DEBUG_clear_full_lines();
+    let mut cells_cleared = Cells::new(&clears);
+    cells_cleared.clear_full_lines();
+    assert_eq!(Cells::new(&cleared), cells_cleared);
+
+    bench("line clear, rows", 100_000, || {
+        let mut matrix = clears.clone();
+        matrix.DEBUG_clear_full_lines();
+        black_box(matrix);
+    });
+
+    let cells = Cells::new(&clears);
+    bench("line clear, cells", 100_000, || {
+        let mut cells = cells.clone();
+        cells.clear_full_lines();
+        black_box(cells);
+    });
+
+    bench("bot, 100 pieces", 5, || {
+        let mut engine = Engine::with_seed(7);
+        let mut bot = Bot::with_weights(Weights::default(), Duration::ZERO);
+        bot.play(&mut engine, 100);
+        black_box(engine.lines_cleared());
+    });
+}
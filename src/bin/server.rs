@@ -0,0 +1,103 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::mpsc,
+    thread,
+};
+
+use tetris::net::{Message, DEFAULT_ADDRESS};
+
+// Pairs up clients as they connect and runs each match on its own thread
+fn main() {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_owned());
+    let listener = TcpListener::bind(&address).expect("Failed to bind server address");
+    println!("Listening on {}", address);
+
+    let mut waiting: Option<TcpStream> = None;
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("Failed to accept client: {}", error);
+                continue;
+            }
+        };
+        // a client that gave up waiting is let go, and the newcomer waits
+        match waiting.take().filter(is_alive) {
+            Some(first) => drop(thread::spawn(move || run_match([first, stream]))),
+            None => {
+                if send(&mut stream, &Message::Waiting).is_ok() {
+                    waiting = Some(stream);
+                }
+            }
+        }
+    }
+}
+
+// Whether the other end is still there, without waiting to find out: a
+// client that hung up reads as the end of the stream straight away
+fn is_alive(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let alive = match stream.peek(&mut [0]) {
+        Ok(read) => read > 0,
+        Err(error) => error.kind() == io::ErrorKind::WouldBlock,
+    };
+    alive && stream.set_nonblocking(false).is_ok()
+}
+
+fn send(stream: &mut TcpStream, message: &Message) -> io::Result<()> {
+    writeln!(stream, "{}", message)
+}
+
+// Relays messages between two players until one of them tops out or leaves
+fn run_match(mut players: [TcpStream; 2]) {
+    let (sender, receiver) = mpsc::channel();
+    for (index, player) in players.iter().enumerate() {
+        let reader = match player.try_clone() {
+            Ok(stream) => BufReader::new(stream),
+            Err(_) => return,
+        };
+        let sender = sender.clone();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let message = match line.ok().and_then(|line| line.parse().ok()) {
+                    Some(message) => message,
+                    None => break,
+                };
+                if sender.send((index, Some(message))).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send((index, None));
+        });
+    }
+    drop(sender);
+
+    for player in &mut players {
+        let _ = send(player, &Message::Start);
+    }
+
+    for (index, message) in receiver {
+        let opponent = 1 - index;
+        match message {
+            Some(message @ (Message::Attack(_) | Message::Board(_))) => {
+                let _ = send(&mut players[opponent], &message);
+            }
+            // leaving mid-match counts as a loss
+            Some(Message::TopOut) | None => {
+                let _ = send(&mut players[index], &Message::End { won: false });
+                let _ = send(&mut players[opponent], &Message::End { won: true });
+                break;
+            }
+            Some(_) => {}
+        }
+    }
+
+    for player in &players {
+        let _ = player.shutdown(Shutdown::Both);
+    }
+}
@@ -0,0 +1,250 @@
+// A heuristic bot that plays through the same inputs as a person. It tries
+// every placement `Engine::placements` finds for the current piece and the
+// held one on a copy of the engine, scores the matrix each leaves, then
+// feeds the best one's inputs back one at a time, so it can be watched at a
+// human pace or run flat out.
+
+use std::{collections::VecDeque, time::Duration};
+
+use crate::engine::{Engine, Input, Matrix};
+
+pub use tbp::ExternalBot;
+
+pub mod tbp;
+
+// How much each feature of a matrix counts towards its score; features that
+// make the stack worse should weigh negatively
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weights {
+    pub aggregate_height: f32,
+    pub holes: f32,
+    pub bumpiness: f32,
+    pub wells: f32,
+    pub lines: f32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            aggregate_height: -0.51,
+            holes: -0.36,
+            bumpiness: -0.18,
+            wells: -0.1,
+            lines: 0.76,
+        }
+    }
+}
+
+impl Weights {
+    // Scores the matrix left behind after a placement that cleared `lines`
+    pub fn evaluate(&self, engine: &Engine, lines: u32) -> f32 {
+        let mut heights = [0; Matrix::WIDTH];
+        let mut filled = [0; Matrix::WIDTH];
+        for (coord, cell) in engine.cells() {
+            if cell.is_some() {
+                heights[coord.x] = coord.y + 1;
+                filled[coord.x] += 1;
+            }
+        }
+
+        let aggregate_height: usize = heights.iter().sum();
+        let holes = aggregate_height - filled.iter().sum::<usize>();
+        let bumpiness: usize = heights
+            .windows(2)
+            .map(|pair| pair[0].abs_diff(pair[1]))
+            .sum();
+        // depth of every column below both its neighbours, walls counting as full
+        let wells: usize = (0..Matrix::WIDTH)
+            .map(|x| {
+                let left = x.checked_sub(1).map_or(Matrix::HEIGHT, |x| heights[x]);
+                let right = heights.get(x + 1).copied().unwrap_or(Matrix::HEIGHT);
+                left.min(right).saturating_sub(heights[x])
+            })
+            .sum();
+
+        self.aggregate_height * aggregate_height as f32
+            + self.holes * holes as f32
+            + self.bumpiness * bumpiness as f32
+            + self.wells * wells as f32
+            + self.lines * lines as f32
+    }
+}
+
+// Feeds a planned placement's inputs out one at a time
+struct Pacer {
+    // time between inputs; zero plays each placement in a single poll
+    delay: Duration,
+    timer: Duration,
+    plan: VecDeque<Input>,
+}
+
+impl Pacer {
+    fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            timer: Duration::ZERO,
+            plan: VecDeque::new(),
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.plan.is_empty()
+    }
+
+    fn start(&mut self, plan: Vec<Input>) {
+        self.plan = plan.into();
+        self.timer = Duration::ZERO;
+    }
+
+    fn clear(&mut self) {
+        self.plan.clear();
+    }
+
+    fn advance(&mut self, delta: Duration) -> Vec<Input> {
+        self.timer += delta;
+        let mut inputs = Vec::new();
+        while self.timer >= self.delay {
+            self.timer -= self.delay;
+            match self.plan.pop_front() {
+                Some(input) => inputs.push(input),
+                None => break,
+            }
+        }
+        inputs
+    }
+}
+
+pub struct Bot {
+    weights: Weights,
+    pacer: Pacer,
+}
+
+impl Bot {
+    pub const DELAY: Duration = Duration::from_millis(50);
+
+    pub fn new() -> Self {
+        Self::with_weights(Weights::default(), Self::DELAY)
+    }
+
+    pub fn with_weights(weights: Weights, delay: Duration) -> Self {
+        Self {
+            weights,
+            pacer: Pacer::new(delay),
+        }
+    }
+
+    // The inputs the bot wants to send after `delta` more time has passed
+    pub fn poll(&mut self, engine: &Engine, delta: Duration) -> Vec<Input> {
+        if engine.is_topped_out() || engine.cursor_info().is_none() {
+            self.pacer.clear();
+            return Vec::new();
+        }
+        if self.pacer.is_idle() {
+            self.pacer.start(self.best_placement(engine));
+        }
+        self.pacer.advance(delta)
+    }
+
+    // Forgets the rest of the current plan, such as when the player takes
+    // the controls back
+    pub fn reset(&mut self) {
+        self.pacer.clear();
+    }
+
+    // Inputs that place the current piece, or the held one, where it scores best
+    pub fn best_placement(&self, engine: &Engine) -> Vec<Input> {
+        let mut best = (f32::NEG_INFINITY, vec![Input::HardDrop]);
+        let mut consider = |engine: &Engine, prefix: &[Input]| {
+            for placement in engine.placements() {
+                if let Some(score) = self.try_placement(engine, &placement.inputs) {
+                    if score > best.0 {
+                        best = (score, [prefix, &placement.inputs].concat());
+                    }
+                }
+            }
+        };
+
+        consider(engine, &[]);
+        let mut held = engine.clone();
+        if held.hold().is_ok() {
+            consider(&held, &[Input::Hold]);
+        }
+        best.1
+    }
+
+    fn try_placement(&self, engine: &Engine, inputs: &[Input]) -> Option<f32> {
+        let mut engine = engine.clone();
+        let lines = engine.lines_cleared();
+        for &input in inputs {
+            engine.input(input);
+        }
+        if engine.is_topped_out() {
+            return None;
+        }
+        Some(
+            self.weights
+                .evaluate(&engine, engine.lines_cleared() - lines),
+        )
+    }
+
+    // Plays `engine` until `pieces` more have been placed or it tops out,
+    // stepping a frame at a time
+    pub fn play(&mut self, engine: &mut Engine, pieces: u32) {
+        const FRAME: Duration = Duration::from_micros(16_667);
+
+        let target = engine.pieces_placed() + pieces;
+        while engine.pieces_placed() < target && !engine.is_topped_out() {
+            for input in self.poll(engine, FRAME) {
+                engine.input(input);
+            }
+            engine.update(FRAME);
+        }
+    }
+}
+
+impl Default for Bot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Whatever is at the controls when the player hands them over
+pub enum Driver {
+    Heuristic(Bot),
+    External(ExternalBot),
+}
+
+impl Driver {
+    pub fn poll(&mut self, engine: &Engine, delta: Duration) -> Vec<Input> {
+        match self {
+            Driver::Heuristic(bot) => bot.poll(engine, delta),
+            Driver::External(bot) => bot.poll(engine, delta),
+        }
+    }
+
+    // Drops whatever was planned for the last piece it saw, so it starts
+    // afresh on whichever piece it is next handed
+    pub fn reset(&mut self) {
+        match self {
+            Driver::Heuristic(bot) => bot.reset(),
+            Driver::External(bot) => bot.reset(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn survives_and_clears_lines() {
+        let mut engine = Engine::with_seed(7);
+        let mut bot = Bot::with_weights(Weights::default(), Duration::ZERO);
+        bot.play(&mut engine, 200);
+
+        assert!(!engine.is_topped_out());
+        assert_eq!(engine.pieces_placed(), 200);
+        // 200 pieces are 800 cells, most of which must have been cleared
+        assert!(engine.lines_cleared() >= 70);
+    }
+}
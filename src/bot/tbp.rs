@@ -0,0 +1,496 @@
+// Plays from an external bot speaking the Tetris Bot Protocol, run as a
+// child process that takes one JSON message a line on stdin and answers on
+// stdout. After its `info` it is sent `rules`; once `ready` it is `start`ed
+// with the matrix, queue, hold and combo, then asked to `suggest`. The first
+// move of each `suggestion` is matched against `Engine::placements`, played
+// through normal inputs and confirmed with `play`, and each piece that comes
+// into view goes out as a `new_piece`. Garbage, or anything else it could
+// not have seen coming, has it `stop`ped and `start`ed again from the
+// engine as it is.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{channel, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use cgmath::Point2;
+use serde::{Deserialize, Serialize};
+
+use super::Pacer;
+use crate::engine::{piece::Kind as PieceKind, piece_set::PieceSet, Color, Engine, Input, Matrix};
+
+// How many pieces past the current one the bot gets to see
+pub const PREVIEWS: usize = 6;
+const BOARD_HEIGHT: usize = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Piece {
+    I,
+    O,
+    T,
+    L,
+    J,
+    S,
+    Z,
+}
+
+impl Piece {
+    // Only the standard tetrominoes have a name in the protocol
+    pub fn of(kind: PieceKind) -> Option<Self> {
+        if !PieceSet::standard()
+            .kinds()
+            .any(|standard| standard == kind)
+        {
+            return None;
+        }
+        Some(match kind.name() {
+            "I" => Piece::I,
+            "O" => Piece::O,
+            "T" => Piece::T,
+            "L" => Piece::L,
+            "J" => Piece::J,
+            "S" => Piece::S,
+            "Z" => Piece::Z,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    #[serde(rename = "type")]
+    pub kind: Piece,
+    pub orientation: Orientation,
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Move {
+    pub location: Location,
+    pub spin: Spin,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrontendMessage {
+    Rules,
+    Start {
+        hold: Option<Piece>,
+        queue: Vec<Piece>,
+        combo: u32,
+        back_to_back: bool,
+        // bottom row first, `Color::letter` per filled cell with `G` for garbage
+        board: Vec<Vec<Option<char>>>,
+    },
+    Suggest,
+    Play {
+        #[serde(rename = "move")]
+        mv: Move,
+    },
+    NewPiece {
+        piece: Piece,
+    },
+    Stop,
+    Quit,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage {
+    Info {
+        name: String,
+        version: String,
+        author: String,
+        #[serde(default)]
+        features: Vec<String>,
+    },
+    Ready,
+    Error {
+        reason: String,
+    },
+    Suggestion {
+        moves: Vec<Move>,
+    },
+}
+
+impl FrontendMessage {
+    // The state a freshly started bot needs to pick up from `engine`, if the
+    // protocol can describe it
+    pub fn start(engine: &Engine) -> Option<Self> {
+        let current = engine.cursor_kind()?;
+        let mut board = vec![vec![None; Matrix::WIDTH]; BOARD_HEIGHT];
+        for (coord, cell) in engine.cells() {
+            board[coord.y][coord.x] = cell.map(|color| match color {
+                Color::Grey => 'G',
+                color => color.letter(),
+            });
+        }
+        let hold = match engine.held() {
+            Some(kind) => Some(Piece::of(kind)?),
+            None => None,
+        };
+        let score = engine.score();
+        Some(FrontendMessage::Start {
+            hold,
+            queue: visible_queue(engine, current)?,
+            combo: score.combo().map_or(0, |combo| combo + 1),
+            back_to_back: score.back_to_back(),
+            board,
+        })
+    }
+}
+
+// The current piece, then the previews
+fn visible_queue(engine: &Engine, current: PieceKind) -> Option<Vec<Piece>> {
+    std::iter::once(current)
+        .chain(engine.queue().take(PREVIEWS))
+        .map(Piece::of)
+        .collect()
+}
+
+impl Location {
+    // The cells covered, sorted like `Placement::cells`. `x` and `y` are the
+    // piece's centre, the cell the others turn around; for I and O that is
+    // the lower left of the middle in the north orientation.
+    pub fn cells(&self) -> Option<Vec<Point2<usize>>> {
+        let north: [(i32, i32); 4] = match self.kind {
+            Piece::I => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+            Piece::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            Piece::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+            Piece::L => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+            Piece::J => [(-1, 1), (-1, 0), (0, 0), (1, 0)],
+            Piece::S => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+            Piece::Z => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+        };
+        let mut cells = north
+            .into_iter()
+            .map(|(x, y)| {
+                let (x, y) = match self.orientation {
+                    Orientation::North => (x, y),
+                    Orientation::East => (y, -x),
+                    Orientation::South => (-x, -y),
+                    Orientation::West => (-y, x),
+                };
+                Point2::new(self.x + x, self.y + y).cast()
+            })
+            .collect::<Option<Vec<_>>>()?;
+        cells.sort_by_key(|coord| (coord.y, coord.x));
+        Some(cells)
+    }
+}
+
+// Where the bot thinks the pieces are, so we know what to tell it next
+struct Mirror {
+    queue: Vec<PieceKind>,
+    hold: Option<PieceKind>,
+}
+
+impl Mirror {
+    fn play(&mut self, kind: PieceKind) {
+        let current = self.queue.remove(0);
+        if current != kind && self.hold.replace(current).is_none() {
+            // holding into an empty slot brings the next piece in
+            self.queue.remove(0);
+        }
+    }
+}
+
+pub struct ExternalBot {
+    child: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Result<BotMessage, String>>,
+    ready: bool,
+    started: bool,
+    thinking: bool,
+    // a suggestion asked for before a reset may still turn up
+    stale: bool,
+    // what the bot was last told, or None once it has fallen out of step
+    mirror: Option<Mirror>,
+    pacer: Pacer,
+    stopped: bool,
+}
+
+impl ExternalBot {
+    // Runs `command`, split on whitespace into a program and its arguments
+    pub fn spawn(command: &str, delay: Duration) -> std::io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words.next().unwrap_or_default();
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let (sender, messages) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let message = match line {
+                    Ok(line) => serde_json::from_str(&line).map_err(|error| error.to_string()),
+                    Err(_) => break,
+                };
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            messages,
+            ready: false,
+            started: false,
+            thinking: false,
+            stale: false,
+            mirror: None,
+            pacer: Pacer::new(delay),
+            stopped: false,
+        })
+    }
+
+    fn send(&mut self, message: &FrontendMessage) {
+        let line = serde_json::to_string(message).unwrap();
+        if writeln!(self.stdin, "{}", line).is_err() {
+            self.stopped = true;
+        }
+    }
+
+    // Like `Bot::reset`; the bot is restarted with the board as it is next
+    // time it is asked for a move
+    pub fn reset(&mut self) {
+        self.pacer.clear();
+        self.stale |= self.thinking;
+        self.thinking = false;
+        self.mirror = None;
+    }
+
+    // The inputs to send after `delta` more time, like `Bot::poll`
+    pub fn poll(&mut self, engine: &Engine, delta: Duration) -> Vec<Input> {
+        loop {
+            match self.messages.try_recv() {
+                Ok(Ok(message)) => self.receive(message, engine),
+                Ok(Err(error)) => eprintln!("Ignoring malformed bot message: {}", error),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if !self.stopped {
+                        eprintln!("The bot has exited");
+                    }
+                    self.stopped = true;
+                    break;
+                }
+            }
+        }
+        if self.stopped || !self.ready || engine.is_topped_out() {
+            return Vec::new();
+        }
+        let current = match engine.cursor_kind() {
+            Some(kind) => kind,
+            None => {
+                self.pacer.clear();
+                return Vec::new();
+            }
+        };
+
+        if self.pacer.is_idle() && !self.thinking {
+            self.sync(engine, current);
+            self.send(&FrontendMessage::Suggest);
+            self.thinking = true;
+        }
+        self.pacer.advance(delta)
+    }
+
+    fn receive(&mut self, message: BotMessage, engine: &Engine) {
+        match message {
+            BotMessage::Info { .. } => self.send(&FrontendMessage::Rules),
+            BotMessage::Ready => self.ready = true,
+            BotMessage::Error { reason } => {
+                eprintln!("The bot gave up: {}", reason);
+                self.stopped = true;
+            }
+            BotMessage::Suggestion { moves } if self.thinking => {
+                let planned = moves.first().and_then(|mv| Some((mv, plan(engine, mv)?)));
+                // one that does not fit is for the board from before the
+                // reset, so the answer for this one is still to come
+                if std::mem::take(&mut self.stale) && planned.is_none() {
+                    return;
+                }
+                self.thinking = false;
+                match planned {
+                    Some((&mv, (kind, inputs))) => {
+                        self.pacer.start(inputs);
+                        self.send(&FrontendMessage::Play { mv });
+                        if let Some(mirror) = self.mirror.as_mut() {
+                            mirror.play(kind);
+                        }
+                    }
+                    None => {
+                        eprintln!("The bot suggested nothing we can play");
+                        self.pacer.start(vec![Input::HardDrop]);
+                        self.mirror = None;
+                    }
+                }
+                // the bot cannot know what garbage the coming lock brings in
+                if engine.pending_garbage() > 0 {
+                    self.mirror = None;
+                }
+            }
+            BotMessage::Suggestion { .. } => self.stale = false,
+        }
+    }
+
+    // Tells the bot about pieces that came into view since its last move, or
+    // restarts it if it no longer agrees with the engine
+    fn sync(&mut self, engine: &Engine, current: PieceKind) {
+        let actual: Vec<PieceKind> = std::iter::once(current)
+            .chain(engine.queue().take(PREVIEWS))
+            .collect();
+        if let Some(mirror) = self.mirror.as_mut() {
+            if mirror.hold == engine.held() && actual.starts_with(&mirror.queue) {
+                let revealed = actual[mirror.queue.len()..].to_vec();
+                mirror.queue = actual;
+                for piece in revealed.into_iter().filter_map(Piece::of) {
+                    self.send(&FrontendMessage::NewPiece { piece });
+                }
+                return;
+            }
+        }
+
+        let start = match FrontendMessage::start(engine) {
+            Some(start) => start,
+            None => {
+                eprintln!("The bot can only play the standard pieces");
+                self.stopped = true;
+                return;
+            }
+        };
+        if self.started {
+            self.send(&FrontendMessage::Stop);
+        }
+        self.send(&start);
+        self.started = true;
+        self.mirror = Some(Mirror {
+            queue: actual,
+            hold: engine.held(),
+        });
+    }
+}
+
+impl Drop for ExternalBot {
+    fn drop(&mut self) {
+        self.send(&FrontendMessage::Quit);
+        let _ = self.child.wait();
+    }
+}
+
+// The piece `mv` places and the inputs that put it there, holding first if
+// it is not the current piece
+fn plan(engine: &Engine, mv: &Move) -> Option<(PieceKind, Vec<Input>)> {
+    let cells = mv.location.cells()?;
+    let find = |engine: &Engine| {
+        engine
+            .placements()
+            .into_iter()
+            .find(|placement| placement.cells == cells)
+            .map(|placement| placement.inputs)
+    };
+
+    let current = engine.cursor_kind()?;
+    if Piece::of(current) == Some(mv.location.kind) {
+        return Some((current, find(engine)?));
+    }
+    let mut held = engine.clone();
+    held.hold().ok()?;
+    let kind = held.cursor_kind()?;
+    let inputs = find(&held)?;
+    Some((kind, [&[Input::Hold][..], &inputs].concat()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn messages_match_the_protocol() {
+        let mv = Move {
+            location: Location {
+                kind: Piece::T,
+                orientation: Orientation::East,
+                x: 4,
+                y: 1,
+            },
+            spin: Spin::None,
+        };
+        assert_eq!(
+            serde_json::to_string(&FrontendMessage::Play { mv }).unwrap(),
+            r#"{"type":"play","move":{"location":{"type":"T","orientation":"east","x":4,"y":1},"spin":"none"}}"#
+        );
+
+        let suggestion = r#"{"type":"suggestion","moves":[{"location":{"type":"T","orientation":"east","x":4,"y":1},"spin":"none"}],"move_info":{}}"#;
+        assert_eq!(
+            serde_json::from_str::<BotMessage>(suggestion).unwrap(),
+            BotMessage::Suggestion { moves: vec![mv] }
+        );
+    }
+
+    #[test]
+    fn suggested_moves_become_inputs() {
+        for name in ["I", "O", "T", "J", "S"] {
+            let kind = PieceSet::standard().kind(name).unwrap();
+            let mut engine = Engine::with_matrix(Matrix::blank());
+            engine.DEBUG_test_cursor_location(kind, kind.spawn());
+            every_placement_has_a_name(&engine, kind);
+        }
+    }
+
+    // every placement the engine can reach is one the bot could name
+    fn every_placement_has_a_name(engine: &Engine, current: PieceKind) {
+        for placement in engine.placements() {
+            let orientations = [
+                Orientation::North,
+                Orientation::East,
+                Orientation::South,
+                Orientation::West,
+            ];
+            let named = orientations.into_iter().find_map(|orientation| {
+                let centres = (0..Matrix::WIDTH as i32).flat_map(|x| (0..3).map(move |y| (x, y)));
+                centres
+                    .map(|(x, y)| Location {
+                        kind: Piece::of(current).unwrap(),
+                        orientation,
+                        x,
+                        y,
+                    })
+                    .find(|location| location.cells().as_ref() == Some(&placement.cells))
+            });
+            let location = named.expect("every placement should have a name");
+            let mv = Move {
+                location,
+                spin: Spin::None,
+            };
+            assert_eq!(plan(engine, &mv), Some((current, placement.inputs)));
+        }
+    }
+}
@@ -0,0 +1,587 @@
+// Player settings: key and controller bindings, handling and how the game
+// looks. They are read from `tetris/config.toml` in the XDG config directory
+// when the game starts; the first time, the defaults are written there with
+// a comment on each, so the file documents itself. A file only needs the
+// settings that differ from the defaults.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    engine::{Input, MoveKind, RotateKind},
+    frontend::Command,
+};
+
+use theme::{Rgb, Theme};
+
+pub mod theme;
+
+const DEFAULT: &str = include_str!("default.toml");
+
+pub const MAX_PREVIEWS: usize = 7;
+
+// Keys other than the single characters
+const NAMED_KEYS: [&str; 15] = [
+    "Left",
+    "Right",
+    "Up",
+    "Down",
+    "Space",
+    "Return",
+    "Escape",
+    "Tab",
+    "Backspace",
+    "Left Shift",
+    "Right Shift",
+    "Left Ctrl",
+    "Right Ctrl",
+    "Left Alt",
+    "Right Alt",
+];
+
+// Controller buttons as SDL names them in its mappings. Stick directions
+// count as buttons, such as `-leftx` for left on the left stick, as do the
+// triggers; either is pressed once past the dead zone.
+const BUTTONS: [&str; 25] = [
+    "a",
+    "b",
+    "x",
+    "y",
+    "back",
+    "guide",
+    "start",
+    "leftstick",
+    "rightstick",
+    "leftshoulder",
+    "rightshoulder",
+    "dpup",
+    "dpdown",
+    "dpleft",
+    "dpright",
+    "lefttrigger",
+    "righttrigger",
+    "-leftx",
+    "+leftx",
+    "-lefty",
+    "+lefty",
+    "-rightx",
+    "+rightx",
+    "-righty",
+    "+righty",
+];
+
+// Every action that can be bound, by its name in the file
+const ACTIONS: [(&str, Command); 12] = [
+    ("left", Command::Input(Input::Move(MoveKind::Left))),
+    ("right", Command::Input(Input::Move(MoveKind::Right))),
+    ("soft_drop", Command::Input(Input::SoftDrop)),
+    ("hard_drop", Command::Input(Input::HardDrop)),
+    (
+        "rotate_clockwise",
+        Command::Input(Input::Rotate(RotateKind::Clockwise)),
+    ),
+    (
+        "rotate_counter_clockwise",
+        Command::Input(Input::Rotate(RotateKind::CounterClockwise)),
+    ),
+    ("hold", Command::Input(Input::Hold)),
+    ("bot", Command::ToggleBot),
+    ("share", Command::Share),
+    ("theme", Command::NextTheme),
+    ("pause", Command::Pause),
+    ("quit", Command::Quit),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub keys: Bindings,
+    // player two's, in versus
+    pub keys2: Bindings,
+    pub buttons: Bindings,
+    pub handling: Handling,
+    pub visuals: Visuals,
+}
+
+// What each key or button does, by its name as in `NAMED_KEYS`, the key's
+// character or its name in `BUTTONS`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bindings(Vec<(String, Command)>);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Handling {
+    // delayed auto shift: how long a move is held before it repeats
+    pub das: Duration,
+    // auto repeat rate: the time between repeats, zero for straight to the wall
+    pub arr: Duration,
+    pub soft_drop_factor: u32,
+    // how far a stick or trigger goes before it counts, from 0 to 1
+    pub dead_zone: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Visuals {
+    pub previews: usize,
+    pub ghost: bool,
+    pub theme: Theme,
+    // patterns on every theme, not just monochrome
+    patterns: bool,
+    // colours from the file that take the place of any theme's
+    palette: Vec<(String, Rgb)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigError(String);
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid config: {}", self.0)
+    }
+}
+
+impl Config {
+    // Where the settings live, if there is a home to put them in
+    pub fn path() -> Option<PathBuf> {
+        Some(xdg_dir("XDG_CONFIG_HOME", ".config")?.join("tetris/config.toml"))
+    }
+
+    // Reads the settings, writing out the defaults if there are none yet.
+    // Failing to write them is not worth stopping the game for.
+    pub fn load_or_create() -> Result<Self, ConfigError> {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+        if path.exists() {
+            return Self::load(path);
+        }
+
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, DEFAULT));
+        match written {
+            Ok(()) => eprintln!("Wrote the default settings to {}", path.display()),
+            Err(error) => eprintln!("Could not write {}: {}", path.display(), error),
+        }
+        Ok(Self::default())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let in_file = |error: String| ConfigError(format!("{}: {}", path.display(), error));
+        let text = fs::read_to_string(path).map_err(|error| in_file(error.to_string()))?;
+        Self::parse(&text).map_err(|ConfigError(error)| in_file(error))
+    }
+
+    // Settings missing from `text` keep their defaults
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let file: File = toml::from_str(text)
+            .map_err(|error| ConfigError(error.to_string().trim_end().to_owned()))?;
+        let defaults: File = toml::from_str(DEFAULT).expect("the default config is valid");
+        let file = file.or(defaults);
+
+        // everything is set once the defaults are in
+        let previews = file.visuals.previews.unwrap_or_default();
+        if previews > MAX_PREVIEWS {
+            return Err(ConfigError(format!(
+                "visuals.previews: {} is more than {}",
+                previews, MAX_PREVIEWS
+            )));
+        }
+        let name = file.visuals.theme.unwrap_or_default();
+        let theme = Theme::named(&name).ok_or_else(|| {
+            let names: Vec<_> = Theme::names().collect();
+            ConfigError(format!(
+                "visuals.theme: {:?} is not one of {}",
+                name,
+                names.join(", ")
+            ))
+        })?;
+        let mut palette = Vec::new();
+        for (name, hex) in file.palette {
+            let rgb = parse_hex(&hex).ok_or_else(|| {
+                ConfigError(format!("palette.{}: {:?} is not #rrggbb", name, hex))
+            })?;
+            if theme.clone().set(&name, rgb).is_err() {
+                return Err(ConfigError(format!(
+                    "palette: there is no colour {:?}",
+                    name
+                )));
+            }
+            palette.push((name, rgb));
+        }
+
+        let mut visuals = Visuals {
+            previews,
+            ghost: file.visuals.ghost.unwrap_or_default(),
+            theme: Theme::default(),
+            patterns: file.visuals.patterns.unwrap_or_default(),
+            palette,
+        };
+        visuals.set_theme(theme);
+        let keys = Bindings::new("keys", file.keys, key_name, &ACTIONS)?;
+        // player two only plays; the rest is player one's
+        let inputs: Vec<_> = ACTIONS
+            .into_iter()
+            .filter(|(_, command)| matches!(command, Command::Input(_)))
+            .collect();
+        let keys2 = Bindings::new("keys2", file.keys2, key_name, &inputs)?;
+        if let Some((name, _)) = keys2
+            .0
+            .iter()
+            .find(|(name, _)| keys.command(name).is_some())
+        {
+            return Err(ConfigError(format!(
+                "keys2: {} is player one's as well",
+                name
+            )));
+        }
+        Ok(Self {
+            keys,
+            keys2,
+            buttons: Bindings::new("buttons", file.buttons, button_name, &ACTIONS)?,
+            handling: file.handling.validate()?,
+            visuals,
+        })
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::parse("").expect("the default config is valid")
+    }
+}
+
+impl Bindings {
+    // What pressing `input`, a key or button, does if anything
+    pub fn command(&self, input: &str) -> Option<Command> {
+        self.0
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(input))
+            .map(|&(_, command)| command)
+    }
+
+    // `canonical` gives the one way of writing each name, or None if it
+    // does not name anything; `actions` are those that can be bound
+    fn new(
+        section: &str,
+        bindings: BTreeMap<String, Vec<String>>,
+        canonical: fn(&str) -> Option<String>,
+        actions: &[(&'static str, Command)],
+    ) -> Result<Self, ConfigError> {
+        let mut bound: Vec<(String, Command, &str)> = Vec::new();
+        for (action, names) in bindings {
+            let (action, command) = actions
+                .iter()
+                .find(|(name, _)| *name == action)
+                .copied()
+                .ok_or_else(|| {
+                    ConfigError(format!("{}: there is no action {:?}", section, action))
+                })?;
+            for name in names {
+                let input = canonical(&name).ok_or_else(|| {
+                    ConfigError(format!("{}.{}: {:?} is not known", section, action, name))
+                })?;
+                match bound.iter().find(|(other, ..)| *other == input) {
+                    Some((_, _, other)) if *other != action => {
+                        return Err(ConfigError(format!(
+                            "{}: {} is bound to both {} and {}",
+                            section, input, other, action
+                        )))
+                    }
+                    Some(_) => {}
+                    None => bound.push((input, command, action)),
+                }
+            }
+        }
+        Ok(Self(
+            bound
+                .into_iter()
+                .map(|(input, command, _)| (input, command))
+                .collect(),
+        ))
+    }
+}
+
+// The key's name as written in `NAMED_KEYS`, or an upper case character
+fn key_name(name: &str) -> Option<String> {
+    if let Some(named) = NAMED_KEYS.iter().find(|key| key.eq_ignore_ascii_case(name)) {
+        return Some((*named).to_owned());
+    }
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) if letter.is_ascii_graphic() => {
+            Some(letter.to_ascii_uppercase().to_string())
+        }
+        _ => None,
+    }
+}
+
+fn button_name(name: &str) -> Option<String> {
+    let name = name.to_ascii_lowercase();
+    BUTTONS.contains(&name.as_str()).then_some(name)
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Config::default().handling
+    }
+}
+
+impl Visuals {
+    // Moves on to the next theme while playing
+    pub fn next_theme(&mut self) {
+        self.set_theme(self.theme.next());
+    }
+
+    // With the player's own colours and patterns on top
+    fn set_theme(&mut self, mut theme: Theme) {
+        for (name, rgb) in &self.palette {
+            let _ = theme.set(name, *rgb);
+        }
+        theme.patterns |= self.patterns;
+        self.theme = theme;
+    }
+}
+
+impl Default for Visuals {
+    fn default() -> Self {
+        Config::default().visuals
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Rgb> {
+    let digits = hex.strip_prefix('#')?;
+    if digits.len() != 6 || !digits.chars().all(|digit| digit.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |at: usize| u8::from_str_radix(&digits[at..at + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+// The directory named by `variable`, or `fallback` under the home directory,
+// as the XDG base directory specification lays out
+pub(crate) fn xdg_dir(variable: &str, fallback: &str) -> Option<PathBuf> {
+    match std::env::var_os(variable) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => Some(PathBuf::from(std::env::var_os("HOME")?).join(fallback)),
+    }
+}
+
+// Every setting is optional so the player's file only needs their changes
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct File {
+    keys: BTreeMap<String, Vec<String>>,
+    keys2: BTreeMap<String, Vec<String>>,
+    buttons: BTreeMap<String, Vec<String>>,
+    handling: RawHandling,
+    visuals: RawVisuals,
+    palette: BTreeMap<String, String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawHandling {
+    das: Option<u64>,
+    arr: Option<u64>,
+    soft_drop_factor: Option<u32>,
+    dead_zone: Option<f32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawVisuals {
+    previews: Option<usize>,
+    ghost: Option<bool>,
+    theme: Option<String>,
+    patterns: Option<bool>,
+}
+
+impl File {
+    // Fills in whatever is missing from `defaults`
+    fn or(self, defaults: File) -> File {
+        // player two's defaults give way to player one's keys too
+        let mut keys2 = defaults.keys2;
+        let taken = taken(&self.keys);
+        for names in keys2.values_mut() {
+            names.retain(|name| !taken.contains(&name.to_lowercase()));
+        }
+        let keys2 = bindings_or(self.keys2, keys2);
+        let keys = bindings_or(self.keys, defaults.keys);
+        let buttons = bindings_or(self.buttons, defaults.buttons);
+        let mut palette = defaults.palette;
+        palette.extend(self.palette);
+        File {
+            keys,
+            keys2,
+            buttons,
+            handling: RawHandling {
+                das: self.handling.das.or(defaults.handling.das),
+                arr: self.handling.arr.or(defaults.handling.arr),
+                soft_drop_factor: self
+                    .handling
+                    .soft_drop_factor
+                    .or(defaults.handling.soft_drop_factor),
+                dead_zone: self.handling.dead_zone.or(defaults.handling.dead_zone),
+            },
+            visuals: RawVisuals {
+                previews: self.visuals.previews.or(defaults.visuals.previews),
+                ghost: self.visuals.ghost.or(defaults.visuals.ghost),
+                theme: self.visuals.theme.or(defaults.visuals.theme),
+                patterns: self.visuals.patterns.or(defaults.visuals.patterns),
+            },
+            palette,
+        }
+    }
+}
+
+// A rebound action loses its default keys, and no default takes a key the
+// player has given to something else, such as from before the action was
+// added
+fn bindings_or(
+    own: BTreeMap<String, Vec<String>>,
+    defaults: BTreeMap<String, Vec<String>>,
+) -> BTreeMap<String, Vec<String>> {
+    let taken = taken(&own);
+    let mut bindings: BTreeMap<_, _> = defaults
+        .into_iter()
+        .map(|(action, mut names)| {
+            names.retain(|name| !taken.contains(&name.to_lowercase()));
+            (action, names)
+        })
+        .collect();
+    bindings.extend(own);
+    bindings
+}
+
+// Every name in `bindings`, in lower case
+fn taken(bindings: &BTreeMap<String, Vec<String>>) -> Vec<String> {
+    bindings
+        .values()
+        .flatten()
+        .map(|name| name.to_lowercase())
+        .collect()
+}
+
+impl RawHandling {
+    fn validate(self) -> Result<Handling, ConfigError> {
+        let soft_drop_factor = self.soft_drop_factor.unwrap_or_default();
+        if soft_drop_factor == 0 {
+            return Err(ConfigError(
+                "handling.soft_drop_factor: must be at least 1".to_owned(),
+            ));
+        }
+        let dead_zone = self.dead_zone.unwrap_or_default();
+        if !(0.0..1.0).contains(&dead_zone) {
+            return Err(ConfigError(format!(
+                "handling.dead_zone: {} is not from 0 up to 1",
+                dead_zone
+            )));
+        }
+        Ok(Handling {
+            das: Duration::from_millis(self.das.unwrap_or_default()),
+            arr: Duration::from_millis(self.arr.unwrap_or_default()),
+            soft_drop_factor,
+            dead_zone,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::Color;
+
+    #[test]
+    fn defaults_fill_in() {
+        let config = Config::parse(
+            "
+            [keys]
+            hold = ['Left Shift', 'c']
+            [handling]
+            arr = 0
+            ",
+        )
+        .unwrap();
+        let hold = Some(Command::Input(Input::Hold));
+        assert_eq!(config.keys.command("C"), hold);
+        assert_eq!(config.keys.command("left shift"), hold);
+        assert_eq!(
+            config.keys.command("Space"),
+            Some(Command::Input(Input::HardDrop))
+        );
+        assert_eq!(config.keys.command("V"), None);
+        assert_eq!(
+            config.buttons.command("+LEFTX"),
+            Some(Command::Input(Input::Move(MoveKind::Right)))
+        );
+        assert_eq!(config.handling.arr, Duration::ZERO);
+        assert_eq!(config.handling.das, Duration::from_millis(167));
+        assert_eq!(config.visuals.theme.rgb(Color::Red), (0xef, 0x29, 0x29));
+
+        // a default gives way to the player's own binding
+        let config = Config::parse("keys.quit = ['Escape', 'Q']").unwrap();
+        assert_eq!(config.keys.command("Escape"), Some(Command::Quit));
+        assert_eq!(config.keys.command("P"), Some(Command::Pause));
+
+        // as does player two's to player one's
+        let config = Config::parse("keys.left = ['A']").unwrap();
+        assert_eq!(config.keys2.command("A"), None);
+        assert_eq!(
+            config.keys2.command("D"),
+            Some(Command::Input(Input::Move(MoveKind::Right)))
+        );
+    }
+
+    #[test]
+    fn palette_outlasts_theme_changes() {
+        let text = "
+            visuals.theme = 'high-contrast'
+            palette.background = '#202020'
+        ";
+        let mut visuals = Config::parse(text).unwrap().visuals;
+        assert_eq!(visuals.theme.name, "high-contrast");
+        assert_eq!(visuals.theme.background, (0x20, 0x20, 0x20));
+
+        let names: Vec<_> = Theme::names().collect();
+        for _ in 0..names.len() {
+            visuals.next_theme();
+            assert_eq!(visuals.theme.background, (0x20, 0x20, 0x20));
+            let patterned = visuals.theme.pattern(Color::Red).is_some();
+            assert_eq!(patterned, visuals.theme.name == "monochrome");
+        }
+        assert_eq!(visuals.theme.name, "high-contrast");
+    }
+
+    #[test]
+    fn bad_settings_are_named() {
+        for (text, problem) in [
+            ("[keys]\nhold = ['Hyper']", "keys.hold"),
+            ("[keys]\nhold = ['X']\nrotate_clockwise = ['x']", "both"),
+            ("[keys]\njump = ['J']", "jump"),
+            ("[buttons]\nhold = ['z']", "buttons.hold"),
+            ("[keys2]\nquit = ['Y']", "quit"),
+            ("[keys2]\nhold = ['Space']", "player one's"),
+            ("[handling]\ndead_zone = 1.5", "dead_zone"),
+            ("[handling]\ndas = -1", "das"),
+            ("[handling]\nsoft_drop_factor = 0", "soft_drop_factor"),
+            ("[visuals]\nprevious = 3", "previous"),
+            ("[visuals]\npreviews = 8", "previews"),
+            ("[palette]\nred = 'red'", "palette.red"),
+            ("[palette]\npink = '#ff00ff'", "pink"),
+            ("[visuals]\ntheme = 'neon'", "monochrome"),
+            ("[visuals", "line 1"),
+        ] {
+            let error = Config::parse(text).unwrap_err().to_string();
+            assert!(error.contains(problem), "{}: {}", text, error);
+        }
+    }
+}
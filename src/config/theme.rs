@@ -0,0 +1,262 @@
+// Named sets of colours for the pieces and everything around them. Any of
+// them can also mark each piece colour with a pattern, so pieces can be told
+// apart without seeing colour at all; monochrome always does.
+
+use crate::engine::Color;
+
+pub type Rgb = (u8, u8, u8);
+
+// What is drawn over a cell, in the background colour, to tell its colour
+// apart from the others
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pattern {
+    Solid,
+    Hollow,
+    Stripes,
+    Bars,
+    Dots,
+    Cross,
+    Diagonal,
+    Checks,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    // per piece colour, in the order `Color` lists them
+    pieces: [Rgb; 8],
+    pub background: Rgb,
+    pub panel: Rgb,
+    // the progress meter in the score panel
+    pub meter: Rgb,
+    pub text: Rgb,
+    pub patterns: bool,
+}
+
+// The first is the default
+const THEMES: [Theme; 7] = [
+    Theme {
+        name: "guideline",
+        pieces: [
+            rgb(0xedd400),
+            rgb(0x729fcf),
+            rgb(0x75507b),
+            rgb(0xf57900),
+            rgb(0x3465a4),
+            rgb(0x73d216),
+            rgb(0xef2929),
+            rgb(0x888a85),
+        ],
+        background: rgb(0x101018),
+        panel: rgb(0x667777),
+        meter: rgb(0x99aaaa),
+        text: rgb(0x101018),
+        patterns: false,
+    },
+    Theme {
+        name: "classic",
+        pieces: [
+            rgb(0xf0f000),
+            rgb(0x00f0f0),
+            rgb(0xa000f0),
+            rgb(0xf0a000),
+            rgb(0x0000f0),
+            rgb(0x00f000),
+            rgb(0xf00000),
+            rgb(0x808080),
+        ],
+        background: rgb(0x000000),
+        panel: rgb(0x303030),
+        meter: rgb(0x606060),
+        text: rgb(0xf0f0f0),
+        patterns: false,
+    },
+    Theme {
+        name: "high-contrast",
+        pieces: [
+            rgb(0xffff00),
+            rgb(0x00ffff),
+            rgb(0xff00ff),
+            rgb(0xff8000),
+            rgb(0x4080ff),
+            rgb(0x00ff00),
+            rgb(0xff0000),
+            rgb(0xffffff),
+        ],
+        background: rgb(0x000000),
+        panel: rgb(0x000000),
+        meter: rgb(0xffffff),
+        text: rgb(0xffffff),
+        patterns: false,
+    },
+    // Okabe and Ito's palette, which keeps apart for red-green blindness
+    Theme {
+        name: "deuteranopia",
+        pieces: [
+            rgb(0xf0e442),
+            rgb(0x56b4e9),
+            rgb(0xcc79a7),
+            rgb(0xe69f00),
+            rgb(0x0072b2),
+            rgb(0x009e73),
+            rgb(0xd55e00),
+            rgb(0x999999),
+        ],
+        background: rgb(0x101018),
+        panel: rgb(0x667777),
+        meter: rgb(0x99aaaa),
+        text: rgb(0x101018),
+        patterns: false,
+    },
+    // Paul Tol's bright scheme, where reds stay light enough to see
+    Theme {
+        name: "protanopia",
+        pieces: [
+            rgb(0xccbb44),
+            rgb(0x66ccee),
+            rgb(0xaa3377),
+            rgb(0xee7733),
+            rgb(0x4477aa),
+            rgb(0x228833),
+            rgb(0xee6677),
+            rgb(0xbbbbbb),
+        ],
+        background: rgb(0x101018),
+        panel: rgb(0x667777),
+        meter: rgb(0x99aaaa),
+        text: rgb(0x101018),
+        patterns: false,
+    },
+    // Paul Tol's vibrant scheme, which does without blue against yellow
+    Theme {
+        name: "tritanopia",
+        pieces: [
+            rgb(0xeeeeee),
+            rgb(0x33bbee),
+            rgb(0xee3377),
+            rgb(0xee7733),
+            rgb(0x0077bb),
+            rgb(0x009988),
+            rgb(0xcc3311),
+            rgb(0x777777),
+        ],
+        background: rgb(0x101018),
+        panel: rgb(0x667777),
+        meter: rgb(0x99aaaa),
+        text: rgb(0x101018),
+        patterns: false,
+    },
+    Theme {
+        name: "monochrome",
+        pieces: [
+            rgb(0xf0f0f0),
+            rgb(0xd0d0d0),
+            rgb(0xb0b0b0),
+            rgb(0xe0e0e0),
+            rgb(0xa0a0a0),
+            rgb(0xc0c0c0),
+            rgb(0x909090),
+            rgb(0x606060),
+        ],
+        background: rgb(0x000000),
+        panel: rgb(0x404040),
+        meter: rgb(0x808080),
+        text: rgb(0xf0f0f0),
+        patterns: true,
+    },
+];
+
+const fn rgb(hex: u32) -> Rgb {
+    ((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+}
+
+impl Theme {
+    pub fn named(name: &str) -> Option<Self> {
+        THEMES.into_iter().find(|theme| theme.name == name)
+    }
+
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        THEMES.into_iter().map(|theme| theme.name)
+    }
+
+    // The one after this, back round to the first after the last
+    pub fn next(&self) -> Self {
+        let index = THEMES.iter().position(|theme| theme.name == self.name);
+        THEMES[index.map_or(0, |index| (index + 1) % THEMES.len())].clone()
+    }
+
+    pub fn rgb(&self, color: Color) -> Rgb {
+        self.pieces[color as usize]
+    }
+
+    // None when the theme goes by colour alone
+    pub fn pattern(&self, color: Color) -> Option<Pattern> {
+        let pattern = match color {
+            Color::Yellow => Pattern::Solid,
+            Color::Cyan => Pattern::Stripes,
+            Color::Purple => Pattern::Dots,
+            Color::Orange => Pattern::Diagonal,
+            Color::Blue => Pattern::Bars,
+            Color::Green => Pattern::Checks,
+            Color::Red => Pattern::Cross,
+            Color::Grey => Pattern::Hollow,
+        };
+        self.patterns.then_some(pattern)
+    }
+
+    // Changes one colour, named as a piece colour or `background`, `panel`,
+    // `meter` or `text`; Err if there is none by that name
+    pub fn set(&mut self, name: &str, rgb: Rgb) -> Result<(), ()> {
+        let swatch = match name {
+            "background" => &mut self.background,
+            "panel" => &mut self.panel,
+            "meter" => &mut self.meter,
+            "text" => &mut self.text,
+            name => &mut self.pieces[Color::from_name(name).ok_or(())? as usize],
+        };
+        *swatch = rgb;
+        Ok(())
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        THEMES[0].clone()
+    }
+}
+
+impl Pattern {
+    // Where to draw, as the left, top, width and height of rectangles in
+    // fractions of the cell
+    pub fn marks(self) -> &'static [(f32, f32, f32, f32)] {
+        match self {
+            Pattern::Solid => &[],
+            Pattern::Hollow => &[(0.25, 0.25, 0.5, 0.5)],
+            Pattern::Stripes => &[(0.0, 0.25, 1.0, 0.125), (0.0, 0.625, 1.0, 0.125)],
+            Pattern::Bars => &[(0.25, 0.0, 0.125, 1.0), (0.625, 0.0, 0.125, 1.0)],
+            Pattern::Dots => &[(0.375, 0.375, 0.25, 0.25)],
+            Pattern::Cross => &[(0.4375, 0.0, 0.125, 1.0), (0.0, 0.4375, 1.0, 0.125)],
+            Pattern::Diagonal => &[
+                (0.0, 0.0, 0.25, 0.25),
+                (0.25, 0.25, 0.25, 0.25),
+                (0.5, 0.5, 0.25, 0.25),
+                (0.75, 0.75, 0.25, 0.25),
+            ],
+            Pattern::Checks => &[(0.0, 0.0, 0.5, 0.5), (0.5, 0.5, 0.5, 0.5)],
+        }
+    }
+
+    // For text cells, which are two characters wide
+    pub fn letters(self) -> [char; 2] {
+        match self {
+            Pattern::Solid => [' ', ' '],
+            Pattern::Hollow => ['[', ']'],
+            Pattern::Stripes => ['=', '='],
+            Pattern::Bars => ['|', '|'],
+            Pattern::Dots => ['(', ')'],
+            Pattern::Cross => ['+', '+'],
+            Pattern::Diagonal => ['\\', '\\'],
+            Pattern::Checks => ['#', '#'],
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::{
+    movegen::{dropped, step},
+    piece::Piece,
+    Coordinate, Input, Matrix, MoveKind, RotateKind,
+};
+
+// Moves and rotations spent beyond the fewest that would have placed each piece
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Finesse {
+    pub faults: u32,
+    // pieces placed, and how many of those without a fault
+    pub pieces: u32,
+    pub clean: u32,
+}
+
+impl Finesse {
+    pub(super) fn judge(&mut self, used: u32, minimum: u32) {
+        let faults = used.saturating_sub(minimum);
+        self.faults += faults;
+        self.pieces += 1;
+        if faults == 0 {
+            self.clean += 1;
+        }
+    }
+
+    // Share of pieces placed without a fault, from 0.0 to 1.0
+    pub fn accuracy(&self) -> f32 {
+        if self.pieces == 0 {
+            1.0
+        } else {
+            self.clean as f32 / self.pieces as f32
+        }
+    }
+}
+
+// The fewest moves and rotations that take `spawned` to where `locked` covers,
+// with gravity switched off. Pieces are compared by the cells they cover: an
+// S spun either way, or an O turned for nothing, ends up in the same place.
+// The search gives up past `limit`, the inputs actually used, since needing
+// more means there was no fault either.
+pub(super) fn minimum_inputs(matrix: &Matrix, spawned: Piece, locked: Piece, limit: u32) -> u32 {
+    const SHIFTS: [Input; 4] = [
+        Input::Move(MoveKind::Left),
+        Input::Move(MoveKind::Right),
+        Input::Rotate(RotateKind::Clockwise),
+        Input::Rotate(RotateKind::CounterClockwise),
+    ];
+    const STEPS: [Input; 5] = [Input::SoftDrop, SHIFTS[0], SHIFTS[1], SHIFTS[2], SHIFTS[3]];
+
+    let target = match sorted_cells(locked) {
+        Some(target) => target,
+        None => return limit,
+    };
+    // Most pieces are lined up where they spawn and hard dropped, which only
+    // needs a small search; tucks and spins need soft drops along the way.
+    // Soft drops are free, as holding the key down is one press however far
+    // the piece goes.
+    let dropped = |state| sorted_cells(dropped(matrix, state)).as_ref() == Some(&target);
+    let in_place = |state| sorted_cells(state).as_ref() == Some(&target);
+    search(matrix, spawned, limit, &SHIFTS, dropped)
+        .or_else(|| search(matrix, spawned, limit, &STEPS, in_place))
+        .unwrap_or(limit)
+}
+
+// 0-1 breadth first search for the cheapest state that is `done`, with free
+// soft drops going to the front of the queue
+fn search(
+    matrix: &Matrix,
+    start: Piece,
+    limit: u32,
+    steps: &[Input],
+    done: impl Fn(Piece) -> bool,
+) -> Option<u32> {
+    let mut costs = HashMap::from([(start, 0)]);
+    let mut frontier = VecDeque::from([(start, 0)]);
+    while let Some((state, cost)) = frontier.pop_front() {
+        if costs[&state] < cost {
+            continue;
+        }
+        if done(state) {
+            return Some(cost);
+        }
+        for &input in steps {
+            let next = match step(matrix, state, input) {
+                Some(next) => next,
+                None => continue,
+            };
+            let step_cost = if input == Input::SoftDrop { 0 } else { 1 };
+            let next_cost = cost + step_cost;
+            if next_cost > limit || costs.get(&next).is_some_and(|&known| known <= next_cost) {
+                continue;
+            }
+            costs.insert(next, next_cost);
+            if step_cost == 0 {
+                frontier.push_front((next, next_cost));
+            } else {
+                frontier.push_back((next, next_cost));
+            }
+        }
+    }
+    None
+}
+
+fn sorted_cells(piece: Piece) -> Option<Vec<Coordinate>> {
+    let mut cells = piece.cells()?;
+    cells.sort_by_key(|coord| (coord.y, coord.x));
+    Some(cells)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::engine::{piece_set::PieceSet, Engine};
+
+    fn place(engine: &mut Engine, inputs: &[Input]) {
+        for &input in inputs {
+            engine.input(input);
+        }
+        engine.input(Input::HardDrop);
+    }
+
+    #[test]
+    fn extra_presses_are_faults() {
+        let mut engine = Engine::with_matrix(Matrix::blank());
+        let t = PieceSet::standard().kind("T").unwrap();
+        engine.DEBUG_test_cursor_location(t, t.spawn());
+        // one move left, then back and forth for nothing
+        let left = Input::Move(MoveKind::Left);
+        let right = Input::Move(MoveKind::Right);
+        place(&mut engine, &[left, left, right]);
+        assert_eq!(engine.finesse().faults, 2);
+
+        engine.update(Duration::ZERO);
+        place(&mut engine, &[]);
+        assert_eq!(engine.finesse().faults, 2);
+        assert_eq!(engine.finesse().pieces, 2);
+        assert_eq!(engine.finesse().clean, 1);
+    }
+
+    #[test]
+    fn turning_an_o_piece_is_a_fault() {
+        let mut engine = Engine::with_matrix(Matrix::blank());
+        let o = PieceSet::standard().kind("O").unwrap();
+        engine.DEBUG_test_cursor_location(o, o.spawn());
+        place(&mut engine, &[Input::Rotate(RotateKind::Clockwise)]);
+        assert_eq!(engine.finesse().faults, 1);
+    }
+}
@@ -0,0 +1,843 @@
+// Fumen, the board editor format the community shares setups in, as read and
+// written by https://github.com/knewjade/tetris-fumen. Only version 115 is
+// supported. A fumen is a list of pages, each a field, an optional piece on
+// top of it and a comment; "#Q=[H](C)QUEUE" comments give the hold, current
+// and queued pieces of a setup.
+
+use std::fmt::{self, Display};
+
+use cgmath::EuclideanSpace;
+
+use super::{
+    piece::{Kind as PieceKind, Piece, Rotation},
+    piece_set::PieceSet,
+    text::EMPTY,
+    Color, Coordinate, Engine, Matrix, Offset,
+};
+
+const PREFIX: &str = "v115@";
+const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+// Fumen's field is taller than the matrix and has a garbage row below it
+const FIELD_TOP: isize = 23;
+const FIELD_BLOCKS: usize = (FIELD_TOP as usize + 1) * Matrix::WIDTH;
+// Cells by their number in fumen, as `Color::letter`s
+const LETTERS: [char; 9] = [EMPTY, 'I', 'L', 'O', 'Z', 'T', 'J', 'S', 'X'];
+// Comment characters are printable ASCII, packed four to five digits
+const COMMENT_BASE: u32 = 96;
+const PREVIEWS: usize = 6;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FumenError(String);
+
+impl Display for FumenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid fumen: {}", self.0)
+    }
+}
+
+impl FumenError {
+    pub fn new(problem: impl Into<String>) -> Self {
+        Self(problem.into())
+    }
+}
+
+fn error<T>(problem: impl Into<String>) -> Result<T, FumenError> {
+    Err(FumenError::new(problem))
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Page {
+    pub matrix: Matrix,
+    pub piece: Option<Placed>,
+    pub comment: String,
+}
+
+// A piece drawn over the matrix; fumen only knows the standard pieces
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placed {
+    pub kind: PieceKind,
+    pub rotation: Rotation,
+    // sorted by row, then column
+    pub cells: Vec<Coordinate>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Quiz {
+    pub hold: Option<PieceKind>,
+    pub current: Option<PieceKind>,
+    pub queue: Vec<PieceKind>,
+}
+
+impl Page {
+    // The pieces named by a "#Q=[H](C)QUEUE" comment
+    pub fn quiz(&self) -> Option<Quiz> {
+        let rest = self.comment.strip_prefix("#Q=[")?;
+        let (hold, rest) = rest.split_once("](")?;
+        let (current, rest) = rest.split_once(')')?;
+        let queue = rest.split_whitespace().next().unwrap_or_default();
+        let optional = |name: &str| match name {
+            "" => Some(None),
+            name => standard_kind(name).map(Some),
+        };
+        Some(Quiz {
+            hold: optional(hold)?,
+            current: optional(current)?,
+            queue: queue
+                .chars()
+                .map(|letter| standard_kind(&letter.to_string()))
+                .collect::<Option<_>>()?,
+        })
+    }
+}
+
+impl Quiz {
+    pub fn comment(&self) -> String {
+        let name = |kind: Option<PieceKind>| kind.map_or("", |kind| kind.name());
+        let queue: String = self.queue.iter().map(|kind| kind.name()).collect();
+        format!("#Q=[{}]({}){}", name(self.hold), name(self.current), queue)
+    }
+}
+
+fn standard_kind(name: &str) -> Option<PieceKind> {
+    PieceSet::standard().kind(name)
+}
+
+// Fumen's number for `kind`, if it is one of the standard pieces
+fn piece_number(kind: PieceKind) -> Option<usize> {
+    if !PieceSet::standard()
+        .kinds()
+        .any(|standard| standard == kind)
+    {
+        return None;
+    }
+    LETTERS[1..8]
+        .iter()
+        .position(|&letter| kind.name() == letter.to_string())
+        .map(|index| index + 1)
+}
+
+pub fn decode(text: &str) -> Result<Vec<Page>, FumenError> {
+    let start = match text.find(PREFIX) {
+        Some(start) => start + PREFIX.len(),
+        None => return error(format!("only {} fumens are supported", PREFIX)),
+    };
+    let mut reader = Reader::new(&text[start..])?;
+
+    let mut pages = Vec::new();
+    let mut field = Field::blank();
+    let mut comment = String::new();
+    // pages still to come that leave the field as it is
+    let mut repeats = 0;
+    while !reader.is_empty() {
+        if repeats > 0 {
+            repeats -= 1;
+        } else {
+            repeats = field.apply_diff(&mut reader)?;
+        }
+
+        let mut action = reader.poll(3)?;
+        let mut take = |base: u32| {
+            let value = action % base;
+            action /= base;
+            value as usize
+        };
+        let (number, rotation, position) = (take(8), take(4), take(FIELD_BLOCKS as u32));
+        let (rise, mirror, _colorize, has_comment, unlocked) =
+            (take(2), take(2), take(2), take(2), take(2));
+
+        if has_comment == 1 {
+            comment = read_comment(&mut reader)?;
+        }
+        let piece = placed(number, rotation, position)?;
+        pages.push(Page {
+            matrix: field.to_matrix()?,
+            piece: piece.clone(),
+            comment: comment.clone(),
+        });
+
+        if unlocked == 0 {
+            if let Some(piece) = piece {
+                field.fill(&piece);
+            }
+            field.clear_lines();
+            if rise == 1 {
+                field.rise();
+            }
+            if mirror == 1 {
+                field.mirror();
+            }
+        }
+    }
+    if pages.is_empty() {
+        return error("there are no pages");
+    }
+    Ok(pages)
+}
+
+pub fn encode(pages: &[Page]) -> Result<String, FumenError> {
+    let mut digits = Vec::new();
+    let mut field = Field::blank();
+    let mut comment = "";
+    // where the count of pages repeating the last field is, while it can grow
+    let mut repeat_count: Option<usize> = None;
+    for (index, page) in pages.iter().enumerate() {
+        let current = Field::from_matrix(&page.matrix);
+        let runs = field.diff(&current);
+        match repeat_count {
+            Some(count) if runs.is_none() && digits[count] < DIGITS.len() as u32 - 1 => {
+                digits[count] += 1;
+            }
+            _ => {
+                let unchanged = [(8 * FIELD_BLOCKS + FIELD_BLOCKS - 1) as u32];
+                for value in runs.as_deref().unwrap_or(&unchanged) {
+                    push(&mut digits, *value, 2);
+                }
+                repeat_count = runs.is_none().then(|| {
+                    digits.push(0);
+                    digits.len() - 1
+                });
+            }
+        }
+        field = current;
+
+        let (number, rotation, position) = match &page.piece {
+            Some(piece) => raw_position(piece)?,
+            None => (0, 0, 0),
+        };
+        let has_comment = page.comment != comment;
+        let colorize = index == 0;
+        let mut action = 0;
+        for (value, base) in [
+            (has_comment as usize, 2),
+            (colorize as usize, 2),
+            (0, 2),
+            (0, 2),
+            (position, FIELD_BLOCKS),
+            (rotation, 4),
+            (number, 8),
+        ] {
+            action = action * base + value;
+        }
+        push(&mut digits, action as u32, 3);
+        if has_comment {
+            write_comment(&mut digits, &page.comment)?;
+            comment = &page.comment;
+        }
+
+        if let Some(piece) = &page.piece {
+            field.fill(piece);
+        }
+        field.clear_lines();
+    }
+
+    let data: String = digits
+        .into_iter()
+        .map(|digit| DIGITS[digit as usize] as char)
+        .collect();
+    Ok(format!("{}{}", PREFIX, data))
+}
+
+fn push(digits: &mut Vec<u32>, mut value: u32, count: usize) {
+    for _ in 0..count {
+        digits.push(value % 64);
+        value /= 64;
+    }
+}
+
+struct Reader {
+    digits: std::vec::IntoIter<u32>,
+}
+
+impl Reader {
+    fn new(data: &str) -> Result<Self, FumenError> {
+        // long fumens are broken up with '?'
+        let digits = data
+            .chars()
+            .filter(|&letter| letter != '?')
+            .map(|letter| {
+                DIGITS
+                    .iter()
+                    .position(|&digit| digit as char == letter)
+                    .map(|digit| digit as u32)
+                    .ok_or_else(|| FumenError(format!("{:?} is not part of a fumen", letter)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            digits: digits.into_iter(),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.digits.len() == 0
+    }
+
+    fn poll(&mut self, count: u32) -> Result<u32, FumenError> {
+        let mut value = 0;
+        for place in 0..count {
+            match self.digits.next() {
+                Some(digit) => value += digit * 64u32.pow(place),
+                None => return error("it ends part way through a page"),
+            }
+        }
+        Ok(value)
+    }
+}
+
+// Cells by fumen number, top row first, with the garbage row last
+#[derive(Clone, Debug, PartialEq)]
+struct Field([u8; FIELD_BLOCKS]);
+
+impl Field {
+    fn blank() -> Self {
+        Self([0; FIELD_BLOCKS])
+    }
+
+    // y runs from -1, the garbage row, up to FIELD_TOP - 1
+    fn index(x: usize, y: isize) -> usize {
+        (FIELD_TOP - 1 - y) as usize * Matrix::WIDTH + x
+    }
+
+    fn from_matrix(matrix: &Matrix) -> Self {
+        let mut field = Self::blank();
+        for (coord, cell) in matrix.cells() {
+            let letter = cell.map_or(EMPTY, Color::letter);
+            let number = LETTERS.iter().position(|&known| known == letter).unwrap();
+            field.0[Self::index(coord.x, coord.y as isize)] = number as u8;
+        }
+        field
+    }
+
+    fn to_matrix(&self) -> Result<Matrix, FumenError> {
+        let mut matrix = Matrix::blank();
+        for y in 0..FIELD_TOP {
+            for x in 0..Matrix::WIDTH {
+                let number = self.0[Self::index(x, y)];
+                if number == 0 {
+                    continue;
+                }
+                if y >= Matrix::HEIGHT as isize {
+                    return error(format!("it is taller than {} rows", Matrix::HEIGHT));
+                }
+                let color = Color::from_letter(LETTERS[number as usize]);
+                matrix.set(Coordinate::new(x, y as usize), color);
+            }
+        }
+        Ok(matrix)
+    }
+
+    // Reads the changes from the last page's field, returning how many more
+    // pages keep it unchanged
+    fn apply_diff(&mut self, reader: &mut Reader) -> Result<u32, FumenError> {
+        let mut repeats = 0;
+        let mut index = 0;
+        while index < FIELD_BLOCKS {
+            let value = reader.poll(2)? as usize;
+            let (diff, run) = (value / FIELD_BLOCKS, value % FIELD_BLOCKS + 1);
+            if diff == 8 && run == FIELD_BLOCKS {
+                repeats = reader.poll(1)?;
+            }
+            let cells = match self.0.get_mut(index..index + run) {
+                Some(cells) => cells,
+                None => return error("a field runs over its end"),
+            };
+            for cell in cells {
+                match (*cell as usize + diff).checked_sub(8) {
+                    Some(number) if number < LETTERS.len() => *cell = number as u8,
+                    _ => return error("a cell changes to nothing"),
+                }
+            }
+            index += run;
+        }
+        Ok(repeats)
+    }
+
+    // The runs of changes from `self` to `next`, or None if there are none
+    fn diff(&self, next: &Field) -> Option<Vec<u32>> {
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for (&from, &to) in self.0.iter().zip(&next.0) {
+            let diff = to as usize + 8 - from as usize;
+            match runs.last_mut() {
+                Some((last, length)) if *last == diff => *length += 1,
+                _ => runs.push((diff, 1)),
+            }
+        }
+        if runs == [(8, FIELD_BLOCKS)] {
+            return None;
+        }
+        Some(
+            runs.into_iter()
+                .map(|(diff, length)| (diff * FIELD_BLOCKS + length - 1) as u32)
+                .collect(),
+        )
+    }
+
+    fn fill(&mut self, piece: &Placed) {
+        let number = piece_number(piece.kind).unwrap() as u8;
+        for cell in &piece.cells {
+            if (cell.y as isize) < FIELD_TOP {
+                self.0[Self::index(cell.x, cell.y as isize)] = number;
+            }
+        }
+    }
+
+    fn rows(&mut self) -> std::slice::ChunksExactMut<'_, u8> {
+        self.0.chunks_exact_mut(Matrix::WIDTH)
+    }
+
+    // Full rows above the garbage row go, and the ones over them fall
+    fn clear_lines(&mut self) {
+        let garbage = Self::index(0, -1);
+        let kept: Vec<[u8; Matrix::WIDTH]> = self.0[..garbage]
+            .chunks_exact(Matrix::WIDTH)
+            .filter(|row| row.contains(&0))
+            .map(|row| row.try_into().unwrap())
+            .collect();
+        let top = garbage - kept.len() * Matrix::WIDTH;
+        self.0[..top].fill(0);
+        self.0[top..garbage].copy_from_slice(kept.as_flattened());
+    }
+
+    // The garbage row pushes the field up by one
+    fn rise(&mut self) {
+        self.0.copy_within(Matrix::WIDTH.., 0);
+        self.0[Self::index(0, -1)..].fill(0);
+    }
+
+    fn mirror(&mut self) {
+        let garbage = FIELD_TOP as usize;
+        for row in self.rows().take(garbage) {
+            row.reverse();
+        }
+    }
+}
+
+// Fumen stores where the piece's rotation centre would be in its own older
+// convention, which puts the O, I, S and Z centres elsewhere in some
+// orientations
+fn centre_adjustment(number: usize, rotation: Rotation) -> Offset {
+    use Rotation::*;
+    let (x, y) = match (LETTERS[number], rotation) {
+        ('O', W) => (1, -1),
+        ('O', S) | ('I', S) | ('Z', W) => (1, 0),
+        ('O', N) | ('I', W) | ('S', N) | ('Z', N) => (0, -1),
+        ('S', E) => (-1, 0),
+        _ => (0, 0),
+    };
+    Offset::new(x, y)
+}
+
+// The cells of piece `number` around its rotation centre, facing north
+fn blocks(number: usize) -> [(isize, isize); 4] {
+    match LETTERS[number] {
+        'I' => [(0, 0), (-1, 0), (1, 0), (2, 0)],
+        'L' => [(0, 0), (-1, 0), (1, 0), (1, 1)],
+        'O' => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        'Z' => [(0, 0), (1, 0), (0, 1), (-1, 1)],
+        'T' => [(0, 0), (-1, 0), (1, 0), (0, 1)],
+        'J' => [(0, 0), (-1, 0), (1, 0), (-1, 1)],
+        _ => [(0, 0), (-1, 0), (0, 1), (1, 1)],
+    }
+}
+
+// Fumen's rotation numbers: reverse, right, spawn and left
+const ROTATIONS: [Rotation; 4] = [Rotation::S, Rotation::E, Rotation::N, Rotation::W];
+
+fn rotation_number(rotation: Rotation) -> usize {
+    ROTATIONS
+        .iter()
+        .position(|&known| known == rotation)
+        .unwrap()
+}
+
+fn rotated_blocks(number: usize, rotation: Rotation) -> impl Iterator<Item = Offset> {
+    blocks(number)
+        .into_iter()
+        .map(move |block| Offset::from(block) * rotation)
+}
+
+fn placed(number: usize, rotation: usize, position: usize) -> Result<Option<Placed>, FumenError> {
+    if number == 0 {
+        return Ok(None);
+    }
+    if number == 8 {
+        return error("the piece is garbage");
+    }
+    let rotation = ROTATIONS[rotation];
+    let raw = Offset::new(
+        (position % Matrix::WIDTH) as isize,
+        FIELD_TOP - 1 - (position / Matrix::WIDTH) as isize,
+    );
+    let centre = raw + centre_adjustment(number, rotation);
+    let mut cells = rotated_blocks(number, rotation)
+        .map(|block| {
+            let cell = centre + block;
+            (0..Matrix::WIDTH as isize)
+                .contains(&cell.x)
+                .then(|| cell.cast())
+                .flatten()
+                .map(Coordinate::from_vec)
+        })
+        .collect::<Option<Vec<_>>>()
+        .map_or_else(|| error("the piece is off the field"), Ok)?;
+    cells.sort_by_key(|coord| (coord.y, coord.x));
+    Ok(Some(Placed {
+        kind: standard_kind(&LETTERS[number].to_string()).unwrap(),
+        rotation,
+        cells,
+    }))
+}
+
+// The piece number, rotation and position fumen stores for `piece`
+fn raw_position(piece: &Placed) -> Result<(usize, usize, usize), FumenError> {
+    let number = match piece_number(piece.kind) {
+        Some(number) => number,
+        None => return error(format!("it cannot show a {} piece", piece.kind.name())),
+    };
+    let mut blocks: Vec<_> = rotated_blocks(number, piece.rotation).collect();
+    blocks.sort_by_key(|block| (block.y, block.x));
+    let cells = || {
+        piece
+            .cells
+            .iter()
+            .map(|coord| coord.to_vec().cast::<isize>().unwrap())
+    };
+    let centre = match cells().min_by_key(|cell| (cell.y, cell.x)) {
+        Some(lowest) => lowest - blocks[0],
+        None => return error("the piece has no cells"),
+    };
+    let mut expected: Vec<_> = cells().collect();
+    expected.sort_by_key(|cell| (cell.y, cell.x));
+    if !blocks.iter().map(|&block| centre + block).eq(expected) {
+        return error(format!(
+            "the {} piece is the wrong shape",
+            piece.kind.name()
+        ));
+    }
+
+    let raw = centre - centre_adjustment(number, piece.rotation);
+    if !(0..Matrix::WIDTH as isize).contains(&raw.x) || !(0..FIELD_TOP).contains(&raw.y) {
+        return error("the piece is off the field");
+    }
+    let position = Field::index(raw.x as usize, raw.y);
+    Ok((number, rotation_number(piece.rotation), position))
+}
+
+fn read_comment(reader: &mut Reader) -> Result<String, FumenError> {
+    let length = reader.poll(2)? as usize;
+    let mut escaped = String::new();
+    while escaped.len() < length {
+        let mut value = reader.poll(5)?;
+        for _ in 0..4 {
+            escaped.push((b' ' + (value % COMMENT_BASE) as u8) as char);
+            value /= COMMENT_BASE;
+        }
+    }
+    escaped.truncate(length);
+    Ok(unescape(&escaped))
+}
+
+fn write_comment(digits: &mut Vec<u32>, comment: &str) -> Result<(), FumenError> {
+    let escaped = escape(comment);
+    if escaped.len() >= 64 * 64 {
+        return error("the comment is too long");
+    }
+    push(digits, escaped.len() as u32, 2);
+    for chunk in escaped.as_bytes().chunks(4) {
+        let value = chunk.iter().rev().fold(0, |value, &byte| {
+            value * COMMENT_BASE + (byte - b' ') as u32
+        });
+        push(digits, value, 5);
+    }
+    Ok(())
+}
+
+// Comments are escaped the way JavaScript's `escape` does it
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for unit in text.encode_utf16() {
+        match char::from_u32(unit as u32) {
+            Some(letter) if letter.is_ascii_alphanumeric() || "@*_+-./".contains(letter) => {
+                escaped.push(letter)
+            }
+            _ if unit < 0x100 => escaped += &format!("%{:02X}", unit),
+            _ => escaped += &format!("%u{:04X}", unit),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut units = Vec::new();
+    let mut rest = text;
+    while let Some(letter) = rest.chars().next() {
+        let hex = |from: usize, length: usize| {
+            let digits = rest.get(from..from + length)?;
+            digits
+                .chars()
+                .all(|digit| digit.is_ascii_hexdigit())
+                .then(|| u16::from_str_radix(digits, 16).ok())?
+        };
+        let (unit, length) = match letter {
+            '%' if rest[1..].starts_with('u') && hex(2, 4).is_some() => (hex(2, 4).unwrap(), 6),
+            '%' if hex(1, 2).is_some() => (hex(1, 2).unwrap(), 3),
+            letter => (letter as u16, letter.len_utf8()),
+        };
+        units.push(unit);
+        rest = &rest[length..];
+    }
+    String::from_utf16_lossy(&units)
+}
+
+impl Engine {
+    // An engine set up as `page` shows it. The pieces of a quiz comment come
+    // first, then bags as usual; without a current piece in the quiz, the
+    // page's piece is where the first one starts.
+    pub fn from_page(page: &Page) -> Result<Self, FumenError> {
+        let mut engine = Engine::with_matrix(page.matrix.clone());
+        let quiz = page.quiz().unwrap_or_default();
+        engine.hold = quiz.hold;
+        engine.bag.extend(quiz.queue.iter().rev());
+        match (quiz.current, &page.piece) {
+            (Some(current), _) => engine.bag.push(current),
+            (None, Some(placed)) => {
+                let piece = cursor(placed)?;
+                if engine.matrix.is_clipping(&piece) {
+                    return error("the piece overlaps the field");
+                }
+                engine.cursor = Some(piece);
+                engine.spawned = Some(piece);
+            }
+            (None, None) => {}
+        }
+        Ok(engine)
+    }
+
+    // The matrix, the falling piece and a quiz comment with the hold and
+    // preview pieces, as one page
+    pub fn to_page(&self) -> Page {
+        let piece = self.cursor.map(|cursor| {
+            let mut cells = cursor.cells().unwrap();
+            cells.sort_by_key(|coord| (coord.y, coord.x));
+            Placed {
+                kind: cursor.kind,
+                rotation: cursor.rotation,
+                cells,
+            }
+        });
+        let quiz = Quiz {
+            hold: self.hold,
+            current: None,
+            queue: self.queue().take(PREVIEWS).collect(),
+        };
+        Page {
+            matrix: self.matrix.clone(),
+            piece,
+            comment: quiz.comment(),
+        }
+    }
+
+    pub fn to_fumen(&self) -> Result<String, FumenError> {
+        encode(&[self.to_page()])
+    }
+}
+
+// The engine's piece covering the same cells as `placed`
+fn cursor(placed: &Placed) -> Result<Piece, FumenError> {
+    let origin = Piece {
+        kind: placed.kind,
+        rotation: placed.rotation,
+        position: Offset::new(0, 0),
+    };
+    let lowest = |cells: &mut Vec<Offset>| {
+        cells.sort_by_key(|cell| (cell.y, cell.x));
+        cells[0]
+    };
+    let mut offsets: Vec<_> = origin.offsets().collect();
+    let mut cells: Vec<_> = placed
+        .cells
+        .iter()
+        .map(|coord| coord.to_vec().cast().unwrap())
+        .collect();
+    let piece = origin.moved_by(lowest(&mut cells) - lowest(&mut offsets));
+    let mut moved = piece.cells().unwrap_or_default();
+    moved.sort_by_key(|coord| (coord.y, coord.x));
+    if moved != placed.cells {
+        return error("the piece does not match its kind");
+    }
+    Ok(piece)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::Input;
+
+    fn kind(name: &str) -> PieceKind {
+        PieceSet::standard().kind(name).unwrap()
+    }
+
+    #[test]
+    fn blank_page() {
+        let pages = decode("v115@vhAAgH").unwrap();
+        assert_eq!(pages, [Page::default()]);
+        assert_eq!(encode(&pages).unwrap(), "v115@vhAAgH");
+    }
+
+    // Fumens as the reference encoder writes them, over the same field
+    #[test]
+    fn reference_pieces() {
+        use Rotation::{E, W};
+        let matrix: Matrix = "
+            X.X..X....
+            XXXXXXX..X
+        "
+        .parse()
+        .unwrap();
+        let cases = [
+            (
+                "v115@RhA8AeA8BeA8DeG8BeA8Jet/I",
+                "T",
+                E,
+                [(1, 2), (1, 3), (2, 3), (1, 4)],
+            ),
+            (
+                "v115@RhA8AeA8BeA8DeG8BeA8JedDJ",
+                "T",
+                W,
+                [(8, 2), (7, 3), (8, 3), (8, 4)],
+            ),
+            (
+                "v115@RhA8AeA8BeA8DeG8BeA8JevBJ",
+                "S",
+                E,
+                [(5, 2), (4, 3), (5, 3), (4, 4)],
+            ),
+            (
+                "v115@RhA8AeA8BeA8DeG8BeA8Jef9I",
+                "S",
+                W,
+                [(6, 3), (5, 4), (6, 4), (5, 5)],
+            ),
+            (
+                "v115@RhA8AeA8BeA8DeG8BeA8JeM7I",
+                "Z",
+                E,
+                [(2, 3), (2, 4), (3, 4), (3, 5)],
+            ),
+            (
+                "v115@RhA8AeA8BeA8DeG8BeA8JecCJ",
+                "Z",
+                W,
+                [(6, 2), (6, 3), (7, 3), (7, 4)],
+            ),
+            (
+                "v115@RhA8AeA8BeA8DeG8BeA8JeJ6I",
+                "I",
+                E,
+                [(0, 2), (0, 3), (0, 4), (0, 5)],
+            ),
+            (
+                "v115@RhA8AeA8BeA8DeG8BeA8Je55I",
+                "I",
+                W,
+                [(9, 3), (9, 4), (9, 5), (9, 6)],
+            ),
+            (
+                "v115@RhA8AeA8BeA8DeG8BeA8JeL8I",
+                "O",
+                E,
+                [(4, 3), (5, 3), (4, 4), (5, 4)],
+            ),
+            (
+                "v115@RhA8AeA8BeA8DeG8BeA8Je73I",
+                "O",
+                W,
+                [(5, 4), (6, 4), (5, 5), (6, 5)],
+            ),
+        ];
+        for (fumen, name, rotation, cells) in cases {
+            let pages = [Page {
+                matrix: matrix.clone(),
+                piece: Some(Placed {
+                    kind: kind(name),
+                    rotation,
+                    cells: cells.map(Coordinate::from).to_vec(),
+                }),
+                comment: String::new(),
+            }];
+            assert_eq!(decode(fumen).unwrap(), pages, "{}", fumen);
+            assert_eq!(encode(&pages).unwrap(), fumen);
+        }
+    }
+
+    #[test]
+    fn pages_round_trip() {
+        let matrix: Matrix = "
+            ZZ........
+            XZZ.XXXXXX
+        "
+        .parse()
+        .unwrap();
+        let t = Placed {
+            kind: kind("T"),
+            rotation: Rotation::W,
+            cells: [(3, 0), (2, 1), (3, 1), (3, 2)]
+                .map(Coordinate::from)
+                .to_vec(),
+        };
+        let comment = "#Q=[I](T)LOSZ ½ spin".to_owned();
+        let first = Page {
+            matrix: matrix.clone(),
+            piece: Some(t),
+            comment: comment.clone(),
+        };
+        // the T clears a line, which the next pages show
+        let cleared: Matrix = "
+            ...T......
+            ZZTT......
+        "
+        .parse()
+        .unwrap();
+        let rest = (0..3).map(|_| Page {
+            matrix: cleared.clone(),
+            piece: None,
+            comment: comment.clone(),
+        });
+        let pages: Vec<_> = std::iter::once(first).chain(rest).collect();
+
+        let fumen = encode(&pages).unwrap();
+        assert_eq!(decode(&fumen).unwrap(), pages);
+        // with the line breaks long fumens get
+        let broken = format!("https://fumen.zui.jp/?{}?{}", &fumen[..20], &fumen[20..]);
+        assert_eq!(decode(&broken).unwrap(), pages);
+    }
+
+    #[test]
+    fn engine_round_trip() {
+        let matrix = "XXXXXX..XX".parse().unwrap();
+        let mut engine = Engine::with_matrix(matrix);
+        engine.update(std::time::Duration::ZERO);
+        engine.input(Input::Hold);
+
+        let page = decode(&engine.to_fumen().unwrap()).unwrap().remove(0);
+        let quiz = page.quiz().unwrap();
+        assert_eq!(quiz.hold, engine.held());
+        assert!(quiz.queue.iter().copied().eq(engine.queue().take(PREVIEWS)));
+
+        let loaded = Engine::from_page(&page).unwrap();
+        assert_eq!(loaded.matrix, engine.matrix);
+        assert_eq!(loaded.cursor_info(), engine.cursor_info());
+        assert_eq!(loaded.held(), engine.held());
+        assert!(loaded
+            .queue()
+            .take(PREVIEWS)
+            .eq(engine.queue().take(PREVIEWS)));
+    }
+
+    #[test]
+    fn bad_fumens_are_rejected() {
+        for text in ["", "v110@vhAAgH", "v115@vhAA", "v115@vh!AgH", "v115@"] {
+            assert!(decode(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use rand::Rng;
+
+use super::{Color, Engine, Matrix, Offset, TopOut};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hole {
+    // every row has its hole in this column
+    Column(usize),
+    // one random column shared by every row
+    Random,
+    // each row picks its own random column
+    Messy,
+}
+
+impl Engine {
+    pub fn add_garbage(&mut self, rows: usize, hole: Hole) -> Result<(), TopOut> {
+        let holes = self.garbage_holes(rows, hole);
+        let mut overflowed = self.matrix.insert_garbage(&holes);
+
+        // the falling piece rises with the stack, but no further than the
+        // top; if the stack then comes up through it there is no room left
+        if let Some(cursor) = self.cursor {
+            let top = cursor.offsets().map(|offset| offset.y).max().unwrap_or(0);
+            let lift = (rows as isize).min(Matrix::HEIGHT as isize - 1 - top);
+            let lifted = cursor.moved_by(Offset::new(0, lift.max(0)));
+            overflowed |= self.matrix.is_clipping(&lifted);
+            self.cursor = Some(lifted);
+        }
+
+        if overflowed {
+            self.cursor = None;
+            self.topped_out = true;
+            Err(TopOut)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Queues garbage from an opponent, inserted when the next piece locks
+    // without clearing a line
+    pub fn receive_garbage(&mut self, lines: u32) {
+        self.pending_garbage += lines;
+    }
+
+    pub fn pending_garbage(&self) -> u32 {
+        self.pending_garbage
+    }
+
+    // Attack built up since the last call, after cancelling pending garbage
+    pub fn take_attack(&mut self) -> u32 {
+        std::mem::take(&mut self.outgoing_attack)
+    }
+
+    // Cancels what is pending with the `attack` of a lock, and puts the rest
+    // in if it `cleared` no lines; returns how many rows went in
+    pub(super) fn settle_garbage(&mut self, cleared: usize, attack: u32) -> usize {
+        let cancelled = attack.min(self.pending_garbage);
+        self.pending_garbage -= cancelled;
+        self.outgoing_attack += attack - cancelled;
+
+        if cleared == 0 && self.pending_garbage > 0 {
+            let rows = std::mem::take(&mut self.pending_garbage) as usize;
+            let _ = self.add_garbage(rows, Hole::Random);
+            return rows;
+        }
+        0
+    }
+
+    // Rows that still hold any garbage
+    pub fn garbage_rows(&self) -> usize {
+        self.matrix.garbage_rows()
+    }
+
+    fn garbage_holes(&mut self, rows: usize, hole: Hole) -> Vec<usize> {
+        match hole {
+            Hole::Column(column) => {
+                assert!(column < Matrix::WIDTH, "Garbage hole out of bounds");
+                vec![column; rows]
+            }
+            Hole::Random => vec![self.rng.gen_range(0..Matrix::WIDTH); rows],
+            Hole::Messy => (0..rows)
+                .map(|_| self.rng.gen_range(0..Matrix::WIDTH))
+                .collect(),
+        }
+    }
+}
+
+impl Matrix {
+    fn garbage_rows(&self) -> usize {
+        self.lines()
+            .filter(|line| line.contains(&Some(Color::Grey)))
+            .count()
+    }
+
+    // Pushes one garbage row per hole in from the bottom, the first hole
+    // ending up lowest. Returns true if filled cells were pushed off the top.
+    fn insert_garbage(&mut self, holes: &[usize]) -> bool {
+        let rows = holes.len().min(Self::HEIGHT);
+        let shifted = rows * Self::WIDTH;
+
+        let overflowed = self.rows[Self::HEIGHT - rows..].iter().any(|&row| row != 0);
+
+        self.rows.copy_within(..Self::HEIGHT - rows, rows);
+        self.colors.copy_within(..Self::SIZE - shifted, shifted);
+        let lines = self.colors[..shifted].chunks_exact_mut(Self::WIDTH);
+        for ((line, row), &hole) in lines.zip(&mut self.rows).zip(holes) {
+            line.fill(Some(Color::Grey));
+            line[hole] = None;
+            *row = Self::FULL_ROW & !(1 << hole);
+        }
+
+        overflowed || holes.len() > Self::HEIGHT
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::{piece_set::PieceSet, Coordinate};
+
+    #[test]
+    fn garbage_shifts_stack_and_cursor() {
+        let mut engine = Engine::with_matrix("....Z.....".parse().unwrap());
+        let t = PieceSet::standard().kind("T").unwrap();
+        engine.DEBUG_test_cursor_location(t, Offset::new(3, 10));
+        let (before, _) = engine.cursor_info().unwrap();
+
+        assert_eq!(engine.add_garbage(2, Hole::Column(7)), Ok(()));
+
+        let expected = "
+            ....Z.....
+            XXXXXXX.XX
+            XXXXXXX.XX
+        ";
+        assert_eq!(engine.matrix, expected.parse().unwrap());
+
+        let (after, _) = engine.cursor_info().unwrap();
+        for (old, new) in before.into_iter().zip(after) {
+            assert_eq!(new, Coordinate::new(old.x, old.y + 2));
+        }
+    }
+
+    #[test]
+    fn attack_cancels_pending_garbage() {
+        let mut engine = Engine::new();
+        engine.receive_garbage(3);
+
+        engine.settle_garbage(4, 4);
+        assert_eq!(engine.pending_garbage(), 0);
+        assert_eq!(engine.take_attack(), 1);
+        assert_eq!(engine.take_attack(), 0);
+
+        engine.receive_garbage(2);
+        engine.settle_garbage(0, 0);
+        assert_eq!(engine.pending_garbage(), 0);
+        assert_eq!(engine.garbage_rows(), 2);
+    }
+
+    #[test]
+    fn garbage_overflow_tops_out() {
+        let mut matrix = Matrix::blank();
+        matrix.set(Coordinate::new(0, Matrix::HEIGHT - 1), Some(Color::Blue));
+        let mut engine = Engine::with_matrix(matrix);
+
+        assert_eq!(engine.add_garbage(1, Hole::Messy), Err(TopOut));
+    }
+
+    #[test]
+    fn garbage_under_a_spawned_piece() {
+        let i = PieceSet::standard().kind("I").unwrap();
+        let mut engine = Engine::new();
+        engine.spawn_kind(i).unwrap();
+        let (before, _) = engine.cursor_info().unwrap();
+
+        // the piece stays on the matrix and can still be placed
+        assert_eq!(engine.add_garbage(3, Hole::Column(0)), Ok(()));
+        assert_eq!(engine.cursor_info().unwrap().0, before);
+        engine.hard_drop();
+        assert!(!engine.is_topped_out());
+        assert_eq!(engine.pieces_placed(), 1);
+
+        // until the stack reaches it
+        let mut engine = Engine::new();
+        engine.spawn_kind(i).unwrap();
+        assert_eq!(
+            engine.add_garbage(Matrix::HEIGHT, Hole::Column(0)),
+            Err(TopOut)
+        );
+        engine.hard_drop();
+        assert!(engine.is_topped_out());
+    }
+}
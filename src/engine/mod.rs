@@ -1,20 +1,26 @@
 use cgmath::EuclideanSpace;
 use geometry::GridIncrement;
-use std::{
-    ops::{Index, IndexMut},
-    slice::ArrayChunks,
-    time::Duration,
-};
+use std::{ops::Index, time::Duration};
 
-use rand::{
-    prelude::{SliceRandom, ThreadRng},
-    thread_rng,
-};
+use rand::{prelude::SliceRandom, rngs::StdRng, SeedableRng};
 
-use self::piece::{Kind as PieceKind, Piece, Rotation};
+use self::{
+    finesse::Finesse,
+    movegen::Placement,
+    piece::{Kind as PieceKind, Piece, Rotation},
+    piece_set::PieceSet,
+    score::Score,
+};
 
+pub mod finesse;
+pub mod fumen;
+pub mod garbage;
 mod geometry;
+pub mod movegen;
 pub mod piece;
+pub mod piece_set;
+pub mod score;
+pub mod text;
 
 type Coordinate = cgmath::Point2<usize>;
 type Offset = cgmath::Vector2<isize>;
@@ -34,23 +40,102 @@ impl MoveKind {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RotateKind {
+    Clockwise,
+    CounterClockwise,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    Move(MoveKind),
+    Rotate(RotateKind),
+    SoftDrop,
+    HardDrop,
+    Hold,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TopOut;
+
+// The latest piece to lock, kept for frontends to animate
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lock {
+    pub cells: Vec<Coordinate>,
+    // the rows it cleared, bottom first, as they were before they went
+    pub cleared: Vec<(usize, [Option<Color>; Matrix::WIDTH])>,
+    // garbage rows pushed in under the stack after it, lifting the rest
+    pub garbage: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Engine {
     matrix: Matrix,
+    pieces: &'static PieceSet,
+    // upcoming pieces, the next one last; never less than a full bag
     bag: Vec<PieceKind>,
-    rng: ThreadRng,
+    rng: StdRng,
     cursor: Option<Piece>,
+    // where the cursor came in, and the moves and rotations spent on it since
+    spawned: Option<Piece>,
+    piece_inputs: u32,
+    hold: Option<PieceKind>,
+    can_hold: bool,
     level: u8,
+    gravity_timer: Duration,
+    lock_timer: Duration,
+    lines_cleared: u32,
+    pieces_placed: u32,
+    score: Score,
+    finesse: Finesse,
+    pending_garbage: u32,
+    outgoing_attack: u32,
+    topped_out: bool,
+    last_lock: Option<Lock>,
 }
 
 impl Engine {
+    const LOCK_DELAY: Duration = Duration::from_millis(500);
+    pub const MAX_LEVEL: u8 = 20;
+
     pub fn new() -> Self {
-        Engine {
+        Self::with_pieces(PieceSet::standard())
+    }
+
+    // Engines with the same seed deal the same pieces and garbage
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(StdRng::seed_from_u64(seed), PieceSet::standard())
+    }
+
+    pub fn with_pieces(pieces: &'static PieceSet) -> Self {
+        Self::with_rng(StdRng::from_entropy(), pieces)
+    }
+
+    fn with_rng(rng: StdRng, pieces: &'static PieceSet) -> Self {
+        let mut engine = Engine {
             matrix: Matrix::blank(),
+            pieces,
             bag: Vec::new(),
-            rng: thread_rng(),
+            rng,
             cursor: None,
+            spawned: None,
+            piece_inputs: 0,
+            hold: None,
+            can_hold: true,
             level: 1,
-        }
+            gravity_timer: Duration::ZERO,
+            lock_timer: Duration::ZERO,
+            lines_cleared: 0,
+            pieces_placed: 0,
+            score: Score::default(),
+            finesse: Finesse::default(),
+            pending_garbage: 0,
+            outgoing_attack: 0,
+            topped_out: false,
+            last_lock: None,
+        };
+        engine.refill_bag();
+        engine
     }
 
     pub fn with_matrix(matrix: Matrix) -> Self {
@@ -61,11 +146,102 @@ impl Engine {
     }
 
     fn refill_bag(&mut self) {
-        // Pull all pieces in bag
-        // shuffle bag
-        debug_assert!(self.bag.is_empty());
-        self.bag.extend_from_slice(PieceKind::ALL.as_slice());
-        self.bag.shuffle(&mut self.rng);
+        // shuffle a full bag in behind the pieces already queued
+        let mut bag: Vec<_> = self.pieces.kinds().collect();
+        bag.shuffle(&mut self.rng);
+        self.bag.splice(0..0, bag);
+    }
+
+    fn next_piece(&mut self) -> PieceKind {
+        let kind = self.bag.pop().unwrap();
+        if self.bag.len() < self.pieces.len() {
+            self.refill_bag();
+        }
+        kind
+    }
+
+    // The pieces coming up, soonest first
+    pub fn queue(&self) -> impl Iterator<Item = PieceKind> + '_ {
+        self.bag.iter().rev().copied()
+    }
+
+    pub fn held(&self) -> Option<PieceKind> {
+        self.hold
+    }
+
+    pub fn pieces(&self) -> &'static PieceSet {
+        self.pieces
+    }
+
+    fn spawn(&mut self) -> Result<(), TopOut> {
+        let kind = self.next_piece();
+        self.spawn_kind(kind)
+    }
+
+    fn spawn_kind(&mut self, kind: PieceKind) -> Result<(), TopOut> {
+        let piece = Piece {
+            kind,
+            rotation: Rotation::N,
+            position: kind.spawn(),
+        };
+        self.gravity_timer = Duration::ZERO;
+        self.lock_timer = Duration::ZERO;
+
+        if self.matrix.is_clipping(&piece) {
+            self.topped_out = true;
+            return Err(TopOut);
+        }
+        self.cursor = Some(piece);
+        self.spawned = Some(piece);
+        self.piece_inputs = 0;
+        Ok(())
+    }
+
+    // Places the cursor, clears any completed lines and brings in the next piece
+    fn lock(&mut self) {
+        // locking out: a piece that comes to rest partly above the matrix
+        // has nowhere to go, so it ends the game rather than being placed
+        if self.cursor.as_ref().is_some_and(Matrix::is_above) {
+            self.cursor = None;
+            self.topped_out = true;
+            return;
+        }
+        self.judge_finesse();
+        let cells = self.cursor.and_then(|cursor| cursor.cells());
+        self.place_cursor();
+        self.pieces_placed += 1;
+        self.can_hold = true;
+
+        let mut rows = Vec::new();
+        self.matrix.line_clear(|matrix, lines| {
+            rows = lines
+                .iter()
+                .map(|&y| (y, matrix.lines().as_slice()[y]))
+                .collect();
+        });
+        let cleared = rows.len();
+        self.lines_cleared += cleared as u32;
+        let attack = self.score.lock(cleared, self.level);
+        let garbage = self.settle_garbage(cleared, attack);
+        self.last_lock = Some(Lock {
+            cells: cells.unwrap_or_default(),
+            cleared: rows,
+            garbage,
+        });
+
+        if !self.topped_out {
+            let _ = self.spawn();
+        }
+    }
+
+    fn judge_finesse(&mut self) {
+        let (spawned, cursor) = match (self.spawned.take(), self.cursor) {
+            (Some(spawned), Some(cursor)) => (spawned, cursor),
+            _ => return,
+        };
+        let used = self.piece_inputs;
+        let minimum = finesse::minimum_inputs(&self.matrix, spawned, cursor, used);
+        self.finesse.judge(used, minimum);
     }
 
     fn place_cursor(&mut self) {
@@ -83,7 +259,20 @@ impl Engine {
 
         let color = cursor.kind.color();
         for coord in cursor.cells().unwrap() {
-            self.matrix[coord] = Some(color);
+            self.matrix.set(coord, Some(color));
+        }
+    }
+
+    pub fn input(&mut self, input: Input) {
+        if matches!(input, Input::Move(_) | Input::Rotate(_)) && self.cursor.is_some() {
+            self.piece_inputs += 1;
+        }
+        match input {
+            Input::Move(kind) => drop(self.move_cursor(kind)),
+            Input::Rotate(kind) => drop(self.rotate_cursor(kind)),
+            Input::SoftDrop => drop(self.soft_drop()),
+            Input::HardDrop => self.hard_drop(),
+            Input::Hold => drop(self.hold()),
         }
     }
 
@@ -102,18 +291,61 @@ impl Engine {
         Ok(())
     }
 
-    pub fn cursor_info(&self) -> Option<([Coordinate; Piece::CELL_COUNT], Color)> {
+    // Swaps the cursor with the held piece, once per piece
+    pub fn hold(&mut self) -> Result<(), ()> {
+        if !self.can_hold {
+            return Err(());
+        }
+        let current = self.cursor.take().ok_or(())?.kind;
+        let _ = match self.hold.replace(current) {
+            Some(held) => self.spawn_kind(held),
+            None => self.spawn(),
+        };
+        self.can_hold = false;
+        Ok(())
+    }
+
+    pub fn rotate_cursor(&mut self, kind: RotateKind) -> Result<(), ()> {
+        let cursor = match self.cursor {
+            Some(cursor) => cursor,
+            None => return Ok(()),
+        };
+
+        let new = self.matrix.kicked(&cursor, kind).ok_or(())?;
+        self.cursor = Some(new);
+        Ok(())
+    }
+
+    // Everywhere the cursor could lock from where it is now
+    pub fn placements(&self) -> Vec<Placement> {
+        self.cursor
+            .map_or_else(Vec::new, |cursor| movegen::placements(&self.matrix, cursor))
+    }
+
+    pub fn cursor_kind(&self) -> Option<PieceKind> {
+        Some(self.cursor?.kind)
+    }
+
+    pub fn cursor_info(&self) -> Option<(Vec<Coordinate>, Color)> {
         let cursor = self.cursor?;
         Some((cursor.cells().unwrap(), cursor.kind.color()))
     }
 
+    // Where the cursor would land if it were hard dropped now
+    pub fn ghost_cells(&self) -> Option<Vec<Coordinate>> {
+        movegen::dropped(&self.matrix, self.cursor?).cells()
+    }
+
+    #[allow(non_snake_case)]
     pub fn DEBUG_test_cursor_location(&mut self, kind: PieceKind, position: Offset) {
         let piece = Piece {
             kind,
             rotation: Rotation::N,
             position,
         };
-        self.cursor = Some(piece)
+        self.cursor = Some(piece);
+        self.spawned = Some(piece);
+        self.piece_inputs = 0;
     }
 
     fn tick_down(&mut self) {
@@ -126,10 +358,7 @@ impl Engine {
     }
 
     fn ticked_down_cursor(&self) -> Option<Piece> {
-        let cursor = match self.cursor {
-            Some(value) => value,
-            None => return None,
-        };
+        let cursor = self.cursor?;
         let new = cursor.moved_by(Offset::new(0, -1));
         if !self.matrix.is_clipping(&new) {
             Some(new)
@@ -138,22 +367,94 @@ impl Engine {
         }
     }
 
+    pub fn soft_drop(&mut self) -> Result<(), ()> {
+        let new = self.ticked_down_cursor().ok_or(())?;
+        self.cursor = Some(new);
+        self.gravity_timer = Duration::ZERO;
+        self.score.soft_drop(1);
+        Ok(())
+    }
+
     pub fn hard_drop(&mut self) {
+        if self.cursor.is_none() {
+            return;
+        }
         // move cursor all the way down
+        let mut cells = 0;
         while let Some(new) = self.ticked_down_cursor() {
             self.cursor = Some(new);
+            cells += 1;
         }
+        self.score.hard_drop(cells);
         // place cursor
-        self.place_cursor();
+        self.lock();
+    }
+
+    // Advances gravity and lock delay by `delta` of game time
+    pub fn update(&mut self, delta: Duration) {
+        if self.topped_out {
+            return;
+        }
+        if self.cursor.is_none() {
+            let _ = self.spawn();
+            return;
+        }
+
+        if self.cursor_has_hit_buttom() {
+            self.lock_timer += delta;
+            if self.lock_timer >= Self::LOCK_DELAY {
+                self.lock();
+            }
+            return;
+        }
+
+        self.lock_timer = Duration::ZERO;
+        self.gravity_timer += delta;
+        while self.gravity_timer >= self.drop_time() {
+            self.gravity_timer -= self.drop_time();
+            match self.ticked_down_cursor() {
+                Some(new) => self.cursor = Some(new),
+                None => break,
+            }
+        }
+    }
+
+    pub fn lines_cleared(&self) -> u32 {
+        self.lines_cleared
+    }
+
+    pub fn pieces_placed(&self) -> u32 {
+        self.pieces_placed
+    }
+
+    pub fn last_lock(&self) -> Option<&Lock> {
+        self.last_lock.as_ref()
+    }
+
+    pub fn score(&self) -> &Score {
+        &self.score
+    }
+
+    pub fn finesse(&self) -> &Finesse {
+        &self.finesse
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn set_level(&mut self, level: u8) {
+        self.level = level.clamp(1, Self::MAX_LEVEL);
+    }
+
+    pub fn is_topped_out(&self) -> bool {
+        self.topped_out
     }
 
     // _ in iter() means new lifetime
     // _ as return means deduced life time
     pub fn cells(&self) -> CellIter<'_> {
-        CellIter {
-            position: Coordinate::origin(),
-            cells: self.matrix.0.iter(),
-        }
+        self.matrix.cells()
     }
     pub fn drop_time(&self) -> Duration {
         let level_index = self.level - 1;
@@ -162,6 +463,12 @@ impl Engine {
     }
 }
 
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Color {
     Yellow,
@@ -171,13 +478,67 @@ pub enum Color {
     Blue,
     Green,
     Red,
+    Grey,
+}
+
+impl Color {
+    // Named after the piece that carries the colour; X is garbage
+    pub fn letter(self) -> char {
+        match self {
+            Color::Yellow => 'O',
+            Color::Cyan => 'I',
+            Color::Purple => 'T',
+            Color::Orange => 'L',
+            Color::Blue => 'J',
+            Color::Green => 'S',
+            Color::Red => 'Z',
+            Color::Grey => 'X',
+        }
+    }
+
+    // As written in piece definition files
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "yellow" => Color::Yellow,
+            "cyan" => Color::Cyan,
+            "purple" => Color::Purple,
+            "orange" => Color::Orange,
+            "blue" => Color::Blue,
+            "green" => Color::Green,
+            "red" => Color::Red,
+            "grey" => Color::Grey,
+            _ => return None,
+        })
+    }
+
+    pub fn from_letter(letter: char) -> Option<Self> {
+        Some(match letter {
+            'O' => Color::Yellow,
+            'I' => Color::Cyan,
+            'T' => Color::Purple,
+            'L' => Color::Orange,
+            'J' => Color::Blue,
+            'S' => Color::Green,
+            'Z' => Color::Red,
+            'X' => Color::Grey,
+            _ => return None,
+        })
+    }
+}
+// Occupancy is kept as a bitmask per row, bit x for column x, so collision
+// tests and line clears work a row at a time. Colours are only read when
+// drawing and sit alongside.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+    rows: [u16; Self::HEIGHT],
+    colors: [Option<Color>; Self::SIZE],
 }
-pub struct Matrix([Option<Color>; Self::SIZE]);
 
 impl Matrix {
     pub const WIDTH: usize = 10;
     pub const HEIGHT: usize = 20;
     const SIZE: usize = Self::WIDTH * Self::HEIGHT;
+    const FULL_ROW: u16 = (1 << Self::WIDTH) - 1;
 
     fn on_matrix(coord: Coordinate) -> bool {
         Self::valid_coord(coord) && coord.y < Self::HEIGHT
@@ -195,19 +556,74 @@ impl Matrix {
         y * Self::WIDTH + x
     }
 
+    // Whether any of `piece` sticks out of the top of the matrix
+    fn is_above(piece: &Piece) -> bool {
+        piece
+            .offsets()
+            .any(|offset| offset.y >= Self::HEIGHT as isize)
+    }
+
     pub fn blank() -> Self {
-        Self([None; Self::SIZE])
+        Self {
+            rows: [0; Self::HEIGHT],
+            colors: [None; Self::SIZE],
+        }
+    }
+
+    pub fn cells(&self) -> CellIter<'_> {
+        CellIter {
+            position: Coordinate::origin(),
+            cells: self.colors.iter(),
+        }
     }
+
+    pub fn set(&mut self, coord: Coordinate, cell: Option<Color>) {
+        assert!(Self::on_matrix(coord));
+        let bit = 1 << coord.x;
+        match cell {
+            Some(_) => self.rows[coord.y] |= bit,
+            None => self.rows[coord.y] &= !bit,
+        }
+        self.colors[Self::indexing(coord)] = cell;
+    }
+
+    // Runs for every state a search visits, so whole rows of the piece are
+    // tested at once. Above the matrix only the walls get in the way.
     fn is_clipping(&self, piece: &Piece) -> bool {
-        let cells = match piece.cells() {
-            Some(value) => value,
-            None => return true,
-        };
-        cells.into_iter().any(|coord| {
-            !Matrix::valid_coord(coord) || (Matrix::on_matrix(coord) || self[coord].is_some())
+        let x = piece.position.x;
+        piece.rows().any(|(y, row)| {
+            let row = u32::from(row);
+            let shifted = if x < 0 {
+                // cells pushed past the left wall; no box is wider than the matrix
+                let past = (-x).min(Self::WIDTH as isize);
+                if row & ((1 << past) - 1) != 0 {
+                    return true;
+                }
+                row >> past
+            } else if x < Self::WIDTH as isize {
+                row << x
+            } else {
+                return true;
+            };
+            if shifted & !u32::from(Self::FULL_ROW) != 0 || y < 0 {
+                return true;
+            }
+            self.rows
+                .get(y as usize)
+                .is_some_and(|&filled| u32::from(filled) & shifted != 0)
         })
     }
 
+    // The first kick of `piece` rotated by `kind` that fits
+    fn kicked(&self, piece: &Piece, kind: RotateKind) -> Option<Piece> {
+        let rotated = piece.rotated(kind);
+        piece
+            .kicks(rotated.rotation)
+            .iter()
+            .map(|&kick| rotated.moved_by(kick))
+            .find(|candidate| !self.is_clipping(candidate))
+    }
+
     fn is_placeable(&self, piece: &Piece) -> bool {
         let cells = match piece.cells() {
             Some(value) => value,
@@ -218,21 +634,53 @@ impl Matrix {
             .all(|coord| Matrix::on_matrix(coord) && self[coord].is_none())
     }
 
-    fn lines(&self) -> ArrayChunks<'_, Option<Color>, { Self::WIDTH }> {
-        self.0.array_chunks()
+    fn lines(&self) -> std::slice::Iter<'_, [Option<Color>; Self::WIDTH]> {
+        self.colors.as_chunks().0.iter()
     }
 
     fn full_lines(&self) -> Vec<usize> {
-        todo!()
+        (0..Self::HEIGHT)
+            .filter(|&index| self.rows[index] == Self::FULL_ROW)
+            .collect()
     }
 
-    fn line_clear(&mut self, animation: impl FnMut(&[usize])) {
-        let lines: Vec<usize> = todo!("identify full lines");
-        animation(lines.as_slice());
-        self.matrix.clear_lines(lines.as_slice());
+    // `animation` sees the full rows before they are cleared
+    fn line_clear(&mut self, mut animation: impl FnMut(&Matrix, &[usize])) {
+        let lines = self.full_lines();
+        animation(self, lines.as_slice());
+        self.clear_lines(lines.as_slice());
     }
+
+    // indices must be in ascending order, as returned by full_lines
     fn clear_lines(&mut self, indices: &[usize]) {
-        todo!("clear lines");
+        for &line in indices.iter().rev() {
+            self.rows.copy_within(line + 1.., line);
+            self.rows[Self::HEIGHT - 1] = 0;
+            let start = (line + 1) * Self::WIDTH;
+            self.colors.copy_within(start.., line * Self::WIDTH);
+            self.colors[Self::SIZE - Self::WIDTH..].fill(None);
+        }
+    }
+
+    // For the benchmarks, which time these against a cell by cell matrix
+    #[allow(non_snake_case)]
+    pub fn DEBUG_is_clipping(&self, kind: PieceKind, rotation: Rotation, position: Offset) -> bool {
+        self.is_clipping(&Piece {
+            kind,
+            rotation,
+            position,
+        })
+    }
+
+    #[allow(non_snake_case)]
+    pub fn DEBUG_clear_full_lines(&mut self) {
+        self.clear_lines(&self.full_lines());
+    }
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Self::blank()
     }
 }
 
@@ -240,13 +688,7 @@ impl Index<Coordinate> for Matrix {
     type Output = Option<Color>;
     fn index(&self, coord: Coordinate) -> &Self::Output {
         assert!(Self::on_matrix(coord));
-        &self.0[Self::indexing(coord)]
-    }
-}
-impl IndexMut<Coordinate> for Matrix {
-    fn index_mut(&mut self, coord: Coordinate) -> &mut Self::Output {
-        assert!(Self::on_matrix(coord));
-        &mut self.0[Self::indexing(coord)]
+        &self.colors[Self::indexing(coord)]
     }
 }
 
@@ -274,14 +716,18 @@ mod test {
     use super::*;
 
     #[test]
+    #[allow(clippy::iter_skip_next)]
     fn cell_iter() {
-        let mut matrix = Matrix::blank();
-        matrix[Coordinate::new(2, 0)] = Some(Color::Blue);
-        matrix[Coordinate::new(3, 1)] = Some(Color::Green);
+        let matrix: Matrix = "
+            ...S......
+            ..J.......
+        "
+        .parse()
+        .unwrap();
 
         let mut iter = CellIter {
             position: Coordinate::origin(),
-            cells: matrix.0.iter(),
+            cells: matrix.colors.iter(),
         };
 
         let first_five = (&mut iter).take(5).collect::<Vec<_>>();
@@ -304,4 +750,83 @@ mod test {
 
         assert!(iter.all(|(_, contents)| contents.is_none()));
     }
+
+    #[test]
+    fn clipping_matches_cells() {
+        let mut matrix = Matrix::blank();
+        for (x, y) in [(0, 0), (1, 0), (4, 1), (9, 3), (5, 19)] {
+            matrix.set(Coordinate::new(x, y), Some(Color::Grey));
+        }
+
+        for kind in PieceSet::standard().kinds() {
+            for rotation in Rotation::ALL {
+                for x in -4..14 {
+                    for y in -4..24 {
+                        let piece = Piece {
+                            kind,
+                            rotation,
+                            position: Offset::new(x, y),
+                        };
+                        let by_cell = piece.offsets().any(|offset| {
+                            offset.x < 0
+                                || offset.x >= Matrix::WIDTH as isize
+                                || offset.y < 0
+                                || (offset.y < Matrix::HEIGHT as isize
+                                    && matrix[Coordinate::from_vec(offset.cast().unwrap())]
+                                        .is_some())
+                        });
+                        assert_eq!(matrix.is_clipping(&piece), by_cell, "{:?}", piece);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hard_drop_clears_lines() {
+        let matrix = "
+            Z.........
+            SSSSSSSSS.
+            SSSSSSSSS.
+        ";
+        let mut engine = Engine::with_matrix(matrix.parse().unwrap());
+        let mut i_piece = Piece {
+            kind: PieceSet::standard().kind("I").unwrap(),
+            rotation: Rotation::N,
+            position: Offset::new(7, 10),
+        };
+        i_piece = i_piece.rotated(RotateKind::Clockwise);
+        engine.cursor = Some(i_piece);
+        engine.hard_drop();
+
+        assert_eq!(engine.lines_cleared(), 2);
+        assert_eq!(engine.pieces_placed(), 1);
+        let lock = engine.last_lock().unwrap();
+        assert_eq!(lock.cells.len(), 4);
+        let cleared: Vec<_> = lock.cleared.iter().map(|&(y, _)| y).collect();
+        assert_eq!(cleared, [0, 1]);
+        assert_eq!(lock.cleared[0].1[9], Some(Color::Cyan));
+        let expected = "
+            .........I
+            Z........I
+        ";
+        assert_eq!(engine.matrix, expected.parse().unwrap());
+    }
+
+    #[test]
+    fn locking_above_the_matrix_tops_out() {
+        let stack = "SSSSSSSSS.\n".repeat(Matrix::HEIGHT - 2);
+        let matrix: Matrix = stack.parse().unwrap();
+        let mut engine = Engine::with_matrix(matrix.clone());
+        engine
+            .spawn_kind(PieceSet::standard().kind("I").unwrap())
+            .unwrap();
+        engine.rotate_cursor(RotateKind::Clockwise).unwrap();
+        engine.hard_drop();
+
+        assert!(engine.is_topped_out());
+        assert_eq!(engine.cursor, None);
+        assert_eq!(engine.pieces_placed(), 0);
+        assert_eq!(engine.matrix, matrix);
+    }
 }
@@ -0,0 +1,146 @@
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
+
+use super::{
+    piece::{Piece, Rotation},
+    Coordinate, Input, Matrix, MoveKind, Offset, RotateKind,
+};
+
+// Somewhere the piece can lock, and the fewest inputs that put it there
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+    // sorted, so placements covering the same cells compare equal
+    pub cells: Vec<Coordinate>,
+    pub rotation: Rotation,
+    // ends with the hard drop that locks the piece
+    pub inputs: Vec<Input>,
+}
+
+// Searches every state `piece` can reach on `matrix` with moves, rotations
+// (kicks included) and soft drops, breadth first so each placement is found
+// with its shortest input sequence first. Tucks under overhangs and spins into
+// slots come out like any other placement.
+pub(super) fn placements(matrix: &Matrix, piece: Piece) -> Vec<Placement> {
+    const STEPS: [Input; 5] = [
+        Input::Move(MoveKind::Left),
+        Input::Move(MoveKind::Right),
+        Input::Rotate(RotateKind::Clockwise),
+        Input::Rotate(RotateKind::CounterClockwise),
+        Input::SoftDrop,
+    ];
+
+    let mut placements = Vec::new();
+    let mut landed = HashSet::new();
+    // each state remembers the state and input it was first reached from
+    let mut visited: HashMap<Piece, Option<(Piece, Input)>> = HashMap::new();
+    let mut frontier = VecDeque::new();
+    if matrix.is_clipping(&piece) {
+        return placements;
+    }
+    visited.insert(piece, None);
+    frontier.push_back(piece);
+
+    while let Some(state) = frontier.pop_front() {
+        let mut cells = dropped(matrix, state).cells().unwrap();
+        cells.sort_by_key(|coord| (coord.y, coord.x));
+        if landed.insert(cells.clone()) {
+            let mut inputs = path(&visited, state);
+            inputs.push(Input::HardDrop);
+            placements.push(Placement {
+                cells,
+                rotation: state.rotation,
+                inputs,
+            });
+        }
+
+        for input in STEPS {
+            let next = match step(matrix, state, input) {
+                Some(next) => next,
+                None => continue,
+            };
+            if let Entry::Vacant(entry) = visited.entry(next) {
+                entry.insert(Some((state, input)));
+                frontier.push_back(next);
+            }
+        }
+    }
+    placements
+}
+
+pub(super) fn step(matrix: &Matrix, piece: Piece, input: Input) -> Option<Piece> {
+    let moved = match input {
+        Input::Move(kind) => piece.moved_by(kind.offset()),
+        Input::Rotate(kind) => return matrix.kicked(&piece, kind),
+        Input::SoftDrop => piece.moved_by(Offset::new(0, -1)),
+        _ => return None,
+    };
+    (!matrix.is_clipping(&moved)).then_some(moved)
+}
+
+pub(super) fn dropped(matrix: &Matrix, mut piece: Piece) -> Piece {
+    while let Some(next) = step(matrix, piece, Input::SoftDrop) {
+        piece = next;
+    }
+    piece
+}
+
+fn path(visited: &HashMap<Piece, Option<(Piece, Input)>>, mut state: Piece) -> Vec<Input> {
+    let mut inputs = Vec::new();
+    while let Some(&(previous, input)) = visited[&state].as_ref() {
+        inputs.push(input);
+        state = previous;
+    }
+    inputs.reverse();
+    inputs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::{piece_set::PieceSet, Engine};
+
+    #[test]
+    fn every_column_on_an_empty_matrix() {
+        let mut engine = Engine::with_matrix(Matrix::blank());
+        let i = PieceSet::standard().kind("I").unwrap();
+        engine.DEBUG_test_cursor_location(i, i.spawn());
+
+        // flat in 7 places, upright in all 10 columns
+        let placements = engine.placements();
+        assert_eq!(placements.len(), 17);
+        for placement in placements {
+            assert_eq!(placement.inputs.last(), Some(&Input::HardDrop));
+            let mut copy = engine.clone();
+            for input in placement.inputs {
+                copy.input(input);
+            }
+            let mut cells: Vec<_> = copy.cells().filter(|(_, cell)| cell.is_some()).collect();
+            cells.sort_by_key(|(coord, _)| (coord.y, coord.x));
+            assert!(cells
+                .iter()
+                .map(|(coord, _)| *coord)
+                .eq(placement.cells.iter().copied()));
+        }
+    }
+
+    #[test]
+    fn tuck_under_overhang() {
+        // a roof over columns 0-2 on row 2, leaving a slot beneath only
+        // reachable by soft dropping and sliding in from the right
+        let matrix = "
+            XXX.......
+            ..........
+            ..........
+        ";
+        let mut engine = Engine::with_matrix(matrix.parse().unwrap());
+        let o = PieceSet::standard().kind("O").unwrap();
+        engine.DEBUG_test_cursor_location(o, o.spawn());
+
+        let tucked = engine
+            .placements()
+            .into_iter()
+            .find(|placement| placement.cells[0] == Coordinate::new(0, 0))
+            .expect("the O piece should fit under the roof");
+        assert!(tucked.inputs.contains(&Input::SoftDrop));
+        assert!(tucked.inputs.contains(&Input::Move(MoveKind::Left)));
+    }
+}
@@ -1,8 +1,13 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
 use cgmath::{EuclideanSpace, Zero};
 
-use super::{Color, Coordinate, Matrix, Offset};
+use super::{piece_set::Shape, Color, Coordinate, Matrix, Offset, RotateKind};
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub(super) struct Piece {
     pub kind: Kind,
     pub position: Offset,
@@ -10,111 +15,162 @@ pub(super) struct Piece {
 }
 
 impl Piece {
-    pub const CELL_COUNT: usize = 4;
-
     pub fn moved_by(&self, offset: Offset) -> Self {
         Self {
             position: self.position + offset,
             ..*self
         }
     }
-    // todo is a divergent type, returns automatically any type
-    pub fn cells(&self) -> Option<[Coordinate; Self::CELL_COUNT]> {
-        let offsets = self.kind.cells().map(self.rotator()).map(self.positioner());
-        let mut coords = [Coordinate::origin(); Self::CELL_COUNT];
-        for (offset, coord_slot) in offsets.into_iter().zip(&mut coords) {
-            let positive_offset = offset.cast::<usize>()?;
-            let coord = Coordinate::from_vec(positive_offset);
-            if Matrix::valid_coord(coord) {
-                *coord_slot = coord;
-            } else {
-                return None;
-            }
+
+    pub fn rotated(&self, kind: RotateKind) -> Self {
+        Self {
+            rotation: self.rotation.rotated(kind),
+            ..*self
         }
+    }
 
-        Some(coords)
+    // Wall kicks to try, in order, when rotating into `to`
+    pub fn kicks(&self, to: Rotation) -> &'static [Offset] {
+        &self.kind.0.kicks[self.rotation.index()][to.index()]
     }
 
-    fn rotator(&self) -> impl Fn(Offset) -> Offset + '_ {
-        |cell| match self.kind {
-            Kind::O => cell,
-            _ => {
-                let grid_offset = self.rotation.intrinsic_offset() * (self.kind.grid_size() - 1);
-                cell * self.rotation + grid_offset
-            }
-        }
+    // Where each cell sits, None if any is off the bottom or the sides
+    pub fn cells(&self) -> Option<Vec<Coordinate>> {
+        self.offsets()
+            .map(|offset| {
+                let coord = Coordinate::from_vec(offset.cast::<usize>()?);
+                Matrix::valid_coord(coord).then_some(coord)
+            })
+            .collect()
     }
 
-    fn positioner(&self) -> impl Fn(Offset) -> Offset {
-        let position = self.position;
-        move |cell| cell + position
+    // Cells relative to the matrix origin, without checking they are on it
+    pub fn offsets(&self) -> impl Iterator<Item = Offset> + '_ {
+        self.kind
+            .cells()
+            .iter()
+            .map(move |&cell| in_box(cell, self.rotation, self.kind.size()) + self.position)
+    }
+
+    // The row each part of the piece is on, with a bit set for every column
+    // it covers relative to `position.x`
+    pub fn rows(&self) -> impl Iterator<Item = (isize, u16)> + '_ {
+        self.kind.0.rows[self.rotation.index()]
+            .iter()
+            .enumerate()
+            .filter(|(_, &row)| row != 0)
+            .map(move |(y, &row)| (self.position.y + y as isize, row))
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum Kind {
-    O,
-    I,
-    T,
-    L,
-    J,
-    S,
-    Z,
+// Where `cell` of a piece in a `size` box ends up once the box is turned
+fn in_box(cell: Offset, rotation: Rotation, size: isize) -> Offset {
+    cell * rotation + rotation.intrinsic_offset() * (size - 1)
 }
 
-impl Kind {
-    pub const ALL: [Self; 7] = [
-        Self::O,
-        Self::I,
-        Self::T,
-        Self::L,
-        Self::J,
-        Self::S,
-        Self::Z,
-    ];
-
-    // adding static references for references, restricts references of values to the static
-    pub fn cells(&self) -> [Offset; Piece::CELL_COUNT] {
-        match self {
-            Self::O => &[(1, 1), (1, 2), (2, 1), (2, 2)],
-            Self::I => &[(0, 2), (1, 2), (2, 2), (3, 2)],
-            Self::T => &[(0, 1), (1, 1), (2, 1), (1, 2)],
-            Self::L => &[(0, 1), (1, 1), (2, 1), (2, 2)],
-            Self::J => &[(0, 2), (0, 1), (1, 1), (2, 1)],
-            Self::S => &[(1, 1), (1, 1), (1, 2), (2, 2)],
-            Self::Z => &[(0, 2), (1, 2), (1, 1), (2, 1)],
+// The rows of a `size` box, bottom up, covered by `cells` in each rotation;
+// bit x is set for column x of the box
+pub(super) fn row_masks(cells: &[Offset], size: isize) -> [Vec<u16>; 4] {
+    Rotation::ALL.map(|rotation| {
+        let mut rows = vec![0; size as usize];
+        for &cell in cells {
+            let cell = in_box(cell, rotation, size);
+            rows[cell.y as usize] |= 1 << cell.x;
         }
-        .map(Offset::from)
+        rows
+    })
+}
+
+// One of the pieces of a `PieceSet`
+#[derive(Clone, Copy)]
+pub struct Kind(&'static Shape);
+
+impl Kind {
+    pub(super) fn new(shape: &'static Shape) -> Self {
+        Self(shape)
     }
 
-    fn grid_size(&self) -> isize {
-        match self {
-            Self::I => 4,
-            _ => 3,
-        }
+    pub fn name(&self) -> &'static str {
+        &self.0.name
+    }
+
+    // In the spawn orientation, inside a `size` x `size` box
+    pub fn cells(&self) -> &'static [Offset] {
+        &self.0.cells
     }
+
+    pub fn size(&self) -> isize {
+        self.0.size
+    }
+
     pub fn color(&self) -> Color {
-        match self {
-            Self::O => Color::Yellow,
-            Self::I => Color::Cyan,
-            Self::T => Color::Purple,
-            Self::L => Color::Orange,
-            Self::J => Color::Blue,
-            Self::S => Color::Green,
-            Self::Z => Color::Red,
-        }
+        self.0.color
+    }
+
+    // Where the bottom left of the piece's box starts
+    pub fn spawn(&self) -> Offset {
+        self.0.spawn
+    }
+}
+
+// Pieces are the same kind only if they come from the same definition
+impl PartialEq for Kind {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0)
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+impl Eq for Kind {}
+
+impl Hash for Kind {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(self.0, state)
+    }
+}
+
+impl fmt::Debug for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Kind({})", self.name())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Rotation {
     N,
-    S,
     E,
+    S,
     W,
 }
 
 impl Rotation {
+    pub fn rotated(self, kind: RotateKind) -> Self {
+        match (self, kind) {
+            (Self::N, RotateKind::Clockwise) | (Self::S, RotateKind::CounterClockwise) => Self::E,
+            (Self::E, RotateKind::Clockwise) | (Self::W, RotateKind::CounterClockwise) => Self::S,
+            (Self::S, RotateKind::Clockwise) | (Self::N, RotateKind::CounterClockwise) => Self::W,
+            (Self::W, RotateKind::Clockwise) | (Self::E, RotateKind::CounterClockwise) => Self::N,
+        }
+    }
+
+    pub const ALL: [Self; 4] = [Self::N, Self::E, Self::S, Self::W];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|rotation| format!("{:?}", rotation) == name)
+    }
+
+    // Whether `to` is a quarter turn away
+    pub fn turned_once(self, to: Self) -> bool {
+        [RotateKind::Clockwise, RotateKind::CounterClockwise]
+            .into_iter()
+            .any(|kind| self.rotated(kind) == to)
+    }
+
     fn intrinsic_offset(&self) -> Offset {
         match self {
             Self::N => Offset::zero(),
@@ -141,18 +197,37 @@ impl std::ops::Mul<Rotation> for Offset {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::engine::piece_set::PieceSet;
 
     #[test]
     fn s_piece_positioning() {
         let z = Piece {
-            kind: Kind::Z,
+            kind: PieceSet::standard().kind("Z").unwrap(),
             position: Offset::new(5, 6),
             rotation: Rotation::W,
         };
 
         assert_eq!(
             z.cells(),
-            Some([(5, 6), (5, 7), (6, 7), (6, 8)].map(Coordinate::from)),
-        )
+            Some(
+                [(5, 6), (5, 7), (6, 7), (6, 8)]
+                    .map(Coordinate::from)
+                    .to_vec()
+            ),
+        );
+
+        let s = Piece {
+            kind: PieceSet::standard().kind("S").unwrap(),
+            position: Offset::new(3, 17),
+            rotation: Rotation::N,
+        };
+        assert_eq!(
+            s.cells(),
+            Some(
+                [(3, 18), (4, 18), (4, 19), (5, 19)]
+                    .map(Coordinate::from)
+                    .to_vec()
+            ),
+        );
     }
 }
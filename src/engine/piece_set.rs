@@ -0,0 +1,340 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Display},
+    path::Path,
+    sync::OnceLock,
+};
+
+use serde::Deserialize;
+
+use super::{
+    piece::{self, Kind, Rotation},
+    Color, Matrix, Offset,
+};
+
+// The format is described at the top of the file itself
+const STANDARD: &str = include_str!("../../pieces/standard.toml");
+
+// The pieces a game is played with, as loaded from a definition file.
+// Sets live for the rest of the program so pieces can point into them.
+#[derive(Debug, PartialEq)]
+pub struct PieceSet {
+    shapes: Vec<Shape>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) struct Shape {
+    pub name: String,
+    // in the spawn orientation, inside a `size` x `size` box
+    pub cells: Vec<Offset>,
+    pub size: isize,
+    pub color: Color,
+    pub spawn: Offset,
+    // indexed by `Rotation::index` of the orientations turned from and to
+    pub kicks: [[Vec<Offset>; 4]; 4],
+    // bitmasks of the box's rows per `Rotation::index`, for collision tests
+    pub rows: [Vec<u16>; 4],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DefinitionError(String);
+
+impl Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid piece definitions: {}", self.0)
+    }
+}
+
+impl PieceSet {
+    // The seven tetrominoes
+    pub fn standard() -> &'static PieceSet {
+        static STANDARD_SET: OnceLock<&'static PieceSet> = OnceLock::new();
+        STANDARD_SET.get_or_init(|| Self::parse(STANDARD).expect("the standard pieces are valid"))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<&'static PieceSet, DefinitionError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| DefinitionError(format!("{}: {}", path.display(), error)))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<&'static PieceSet, DefinitionError> {
+        let file: File =
+            toml::from_str(text).map_err(|error| DefinitionError(error.to_string()))?;
+        if file.piece.is_empty() {
+            return Err(DefinitionError("there are no pieces".to_owned()));
+        }
+
+        let mut names = HashSet::new();
+        let mut shapes = Vec::new();
+        for raw in file.piece {
+            let shape = raw.validate()?;
+            if !names.insert(shape.name.clone()) {
+                return Err(DefinitionError(format!("{} is defined twice", shape.name)));
+            }
+            shapes.push(shape);
+        }
+        Ok(Box::leak(Box::new(PieceSet { shapes })))
+    }
+
+    // In the order they were defined
+    pub fn kinds(&'static self) -> impl Iterator<Item = Kind> {
+        self.shapes.iter().map(Kind::new)
+    }
+
+    pub fn kind(&'static self, name: &str) -> Option<Kind> {
+        self.kinds().find(|kind| kind.name() == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+}
+
+#[derive(Deserialize)]
+struct File {
+    piece: Vec<RawShape>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawShape {
+    name: String,
+    color: String,
+    size: isize,
+    cells: Vec<[isize; 2]>,
+    spawn: Option<[isize; 2]>,
+    #[serde(default)]
+    kicks: RawKicks,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawKicks {
+    Named(String),
+    Table(BTreeMap<String, Vec<[isize; 2]>>),
+}
+
+impl Default for RawKicks {
+    fn default() -> Self {
+        RawKicks::Named("srs".to_owned())
+    }
+}
+
+impl RawShape {
+    fn validate(self) -> Result<Shape, DefinitionError> {
+        let name = self.name;
+        let error = |problem: String| DefinitionError(format!("{}: {}", name, problem));
+        if name.is_empty() {
+            return Err(DefinitionError("a piece has no name".to_owned()));
+        }
+
+        let color = Color::from_name(&self.color)
+            .ok_or_else(|| error(format!("there is no colour {:?}", self.color)))?;
+        // garbage is told apart from the stack by its colour
+        if color == Color::Grey {
+            return Err(error("grey is kept for garbage".to_owned()));
+        }
+
+        let size = self.size;
+        if !(1..=Matrix::WIDTH as isize).contains(&size) {
+            return Err(error(format!("size {} does not fit the matrix", size)));
+        }
+
+        if self.cells.is_empty() {
+            return Err(error("there are no cells".to_owned()));
+        }
+        let mut cells = Vec::new();
+        for [x, y] in self.cells {
+            let cell = Offset::new(x, y);
+            if !(0..size).contains(&x) || !(0..size).contains(&y) {
+                return Err(error(format!("cell ({}, {}) is outside its box", x, y)));
+            }
+            if cells.contains(&cell) {
+                return Err(error(format!("cell ({}, {}) is listed twice", x, y)));
+            }
+            cells.push(cell);
+        }
+        if !is_connected(&cells) {
+            return Err(error("the cells are not all joined up".to_owned()));
+        }
+
+        // centred, with the highest cell on the top row
+        let top = cells.iter().map(|cell| cell.y).max().unwrap();
+        let spawn = self.spawn.map_or_else(
+            || {
+                Offset::new(
+                    (Matrix::WIDTH as isize - size) / 2,
+                    Matrix::HEIGHT as isize - 1 - top,
+                )
+            },
+            Offset::from,
+        );
+        let on_matrix = |cell: &Offset| {
+            let coord = cell + spawn;
+            (0..Matrix::WIDTH as isize).contains(&coord.x)
+                && (0..Matrix::HEIGHT as isize).contains(&coord.y)
+        };
+        if !cells.iter().all(on_matrix) {
+            return Err(error("it spawns outside the matrix".to_owned()));
+        }
+
+        let kicks = kick_table(self.kicks).map_err(error)?;
+        Ok(Shape {
+            name,
+            rows: piece::row_masks(&cells, size),
+            cells,
+            size,
+            color,
+            spawn,
+            kicks,
+        })
+    }
+}
+
+fn is_connected(cells: &[Offset]) -> bool {
+    let mut reached = vec![cells[0]];
+    let mut index = 0;
+    while let Some(&cell) = reached.get(index) {
+        for step in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let neighbour = cell + Offset::from(step);
+            if cells.contains(&neighbour) && !reached.contains(&neighbour) {
+                reached.push(neighbour);
+            }
+        }
+        index += 1;
+    }
+    reached.len() == cells.len()
+}
+
+type Kicks = [[Vec<Offset>; 4]; 4];
+
+fn kick_table(raw: RawKicks) -> Result<Kicks, String> {
+    use Rotation::*;
+
+    let mut kicks: Kicks = Default::default();
+    let mut set = |from: Rotation, to: Rotation, offsets: &[(isize, isize)]| {
+        kicks[from.index()][to.index()] = offsets.iter().copied().map(Offset::from).collect();
+    };
+    // turns that are not listed stay put or fail
+    for from in Rotation::ALL {
+        for to in Rotation::ALL {
+            set(from, to, &[(0, 0)]);
+        }
+    }
+
+    match raw {
+        RawKicks::Named(name) => match name.as_str() {
+            "none" => {}
+            "srs" => {
+                let right = [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)];
+                let left = [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)];
+                let from_right = [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)];
+                let from_left = [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)];
+                for (from, to, offsets) in [
+                    (N, E, right),
+                    (S, E, right),
+                    (E, N, from_right),
+                    (E, S, from_right),
+                    (S, W, left),
+                    (N, W, left),
+                    (W, S, from_left),
+                    (W, N, from_left),
+                ] {
+                    set(from, to, &offsets);
+                }
+            }
+            "srs-i" => {
+                for (turns, offsets) in [
+                    (
+                        [(N, E), (W, S)],
+                        [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+                    ),
+                    (
+                        [(E, N), (S, W)],
+                        [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+                    ),
+                    (
+                        [(E, S), (N, W)],
+                        [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+                    ),
+                    (
+                        [(S, E), (W, N)],
+                        [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+                    ),
+                ] {
+                    for (from, to) in turns {
+                        set(from, to, &offsets);
+                    }
+                }
+            }
+            name => return Err(format!("there is no kick table {:?}", name)),
+        },
+        RawKicks::Table(table) => {
+            for (turn, offsets) in table {
+                let (from, to) = turn
+                    .split_once('-')
+                    .and_then(|(from, to)| {
+                        Some((Rotation::from_name(from)?, Rotation::from_name(to)?))
+                    })
+                    .filter(|&(from, to)| from.turned_once(to))
+                    .ok_or_else(|| format!("{:?} is not a quarter turn like \"N-E\"", turn))?;
+                if offsets.is_empty() {
+                    return Err(format!("the {} kicks are empty", turn));
+                }
+                let offsets: Vec<_> = offsets.into_iter().map(|[x, y]| (x, y)).collect();
+                set(from, to, &offsets);
+            }
+        }
+    }
+    Ok(kicks)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shipped_sets_are_valid() {
+        for text in [
+            STANDARD,
+            include_str!("../../pieces/tromino.toml"),
+            include_str!("../../pieces/pentomino.toml"),
+        ] {
+            assert!(PieceSet::parse(text).is_ok());
+        }
+        assert_eq!(PieceSet::standard().len(), 7);
+    }
+
+    #[test]
+    fn bad_definitions_are_rejected() {
+        let piece = |cells: &str, extra: &str| {
+            format!(
+                "[[piece]]\nname = \"S\"\ncolor = \"green\"\nsize = 3\ncells = {}\n{}",
+                cells, extra
+            )
+        };
+        let valid = "[[0, 1], [1, 1], [1, 2], [2, 2]]";
+        assert!(PieceSet::parse(&piece(valid, "")).is_ok());
+
+        for text in [
+            String::new(),
+            piece("[[1, 1], [1, 1], [1, 2], [2, 2]]", ""),
+            piece("[[0, 0], [2, 2]]", ""),
+            piece("[[0, 3]]", ""),
+            piece(valid, "spawn = [9, 0]"),
+            piece(valid, "kicks = \"sideways\""),
+            piece(valid, "kicks = { N-S = [[0, 0]] }"),
+            piece(valid, "").replace("green", "mauve"),
+            piece(valid, "").replace("green", "grey"),
+            [piece(valid, ""), piece(valid, "")].join("\n"),
+        ] {
+            assert!(PieceSet::parse(&text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Score {
+    pub points: u32,
+    // garbage lines sent, per the guideline attack table
+    pub attack: u32,
+    // singles, doubles, triples and tetrises
+    pub clears: [u32; 4],
+    pub max_combo: u32,
+    combo: Option<u32>,
+    back_to_back: bool,
+}
+
+impl Score {
+    const CLEAR_POINTS: [u32; 4] = [100, 300, 500, 800];
+    const CLEAR_ATTACK: [u32; 4] = [0, 1, 2, 4];
+    const COMBO_ATTACK: [u32; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+
+    // Scores a locked piece that cleared `lines` rows; returns the attack it sends
+    pub(super) fn lock(&mut self, lines: usize, level: u8) -> u32 {
+        if lines == 0 {
+            self.combo = None;
+            return 0;
+        }
+
+        let level = level as u32;
+        let index = lines.min(4) - 1;
+        let difficult = lines >= 4;
+        let back_to_back = difficult && self.back_to_back;
+
+        let mut points = Self::CLEAR_POINTS[index] * level;
+        let mut attack = Self::CLEAR_ATTACK[index];
+        if back_to_back {
+            points += points / 2;
+            attack += 1;
+        }
+
+        let combo = self.combo.map_or(0, |combo| combo + 1);
+        points += 50 * combo * level;
+        attack += Self::COMBO_ATTACK[(combo as usize).min(Self::COMBO_ATTACK.len() - 1)];
+
+        self.clears[index] += 1;
+        self.points += points;
+        self.attack += attack;
+        self.combo = Some(combo);
+        self.max_combo = self.max_combo.max(combo);
+        self.back_to_back = difficult;
+        attack
+    }
+
+    pub(super) fn soft_drop(&mut self, cells: u32) {
+        self.points += cells;
+    }
+
+    pub(super) fn hard_drop(&mut self, cells: u32) {
+        self.points += 2 * cells;
+    }
+
+    pub fn combo(&self) -> Option<u32> {
+        self.combo
+    }
+
+    pub fn back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn back_to_back_tetris_with_combo() {
+        let mut score = Score::default();
+        assert_eq!(score.lock(4, 1), 4);
+        assert_eq!(score.lock(4, 1), 4 + 1);
+        assert_eq!(score.points, 800 + 1200 + 50);
+        assert_eq!(score.clears, [0, 0, 0, 2]);
+
+        assert_eq!(score.lock(0, 1), 0);
+        assert_eq!(score.combo(), None);
+        assert!(score.back_to_back());
+    }
+}
@@ -0,0 +1,166 @@
+// Matrices as text: one line per row, top row first, with a `Color::letter`
+// for each filled cell and `.` for an empty one. An engine also marks its
+// falling piece with `@`. When parsing, rows line up with the bottom of the
+// matrix, so only the interesting ones need writing, and indentation and
+// blank lines are ignored.
+
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use super::{Color, Coordinate, Engine, Matrix};
+
+pub const EMPTY: char = '.';
+pub const CURSOR: char = '@';
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseMatrixError(String);
+
+impl Display for ParseMatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid matrix: {}", self.0)
+    }
+}
+
+impl Matrix {
+    // Also returns the cells marked as the falling piece
+    pub fn parse_with_cursor(text: &str) -> Result<(Self, Vec<Coordinate>), ParseMatrixError> {
+        let lines: Vec<_> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        if lines.len() > Self::HEIGHT {
+            return Err(ParseMatrixError(format!(
+                "{} rows is more than {}",
+                lines.len(),
+                Self::HEIGHT
+            )));
+        }
+
+        let mut matrix = Self::blank();
+        let mut cursor = Vec::new();
+        for (y, line) in lines.into_iter().rev().enumerate() {
+            if line.chars().count() != Self::WIDTH {
+                return Err(ParseMatrixError(format!(
+                    "{:?} is not {} cells wide",
+                    line,
+                    Self::WIDTH
+                )));
+            }
+            for (x, letter) in line.chars().enumerate() {
+                let coord = Coordinate::new(x, y);
+                match letter {
+                    EMPTY => {}
+                    CURSOR => cursor.push(coord),
+                    letter => {
+                        let color = Color::from_letter(letter).ok_or_else(|| {
+                            ParseMatrixError(format!("{:?} is not a cell", letter))
+                        })?;
+                        matrix.set(coord, Some(color));
+                    }
+                }
+            }
+        }
+        cursor.sort_by_key(|coord| (coord.y, coord.x));
+        Ok((matrix, cursor))
+    }
+
+    fn write_rows(&self, f: &mut fmt::Formatter<'_>, cursor: &[Coordinate]) -> fmt::Result {
+        for y in (0..Self::HEIGHT).rev() {
+            for x in 0..Self::WIDTH {
+                let coord = Coordinate::new(x, y);
+                let letter = match self[coord] {
+                    _ if cursor.contains(&coord) => CURSOR,
+                    Some(color) => color.letter(),
+                    None => EMPTY,
+                };
+                write!(f, "{}", letter)?;
+            }
+            if y > 0 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_rows(f, &[])
+    }
+}
+
+// Without a falling piece, so a marked one is an error
+impl FromStr for Matrix {
+    type Err = ParseMatrixError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match Self::parse_with_cursor(text)? {
+            (matrix, cursor) if cursor.is_empty() => Ok(matrix),
+            _ => Err(ParseMatrixError(format!(
+                "{:?} marks a falling piece",
+                CURSOR
+            ))),
+        }
+    }
+}
+
+impl Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cursor = self.cursor_info().map(|(cells, _)| cells);
+        self.matrix
+            .write_rows(f, cursor.as_deref().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::piece_set::PieceSet;
+
+    #[test]
+    fn round_trip() {
+        let text = "
+            ....T.....
+            ...TTT...I
+            ZZ.OO.XXXI
+        ";
+        let matrix: Matrix = text.parse().unwrap();
+        assert_eq!(matrix[Coordinate::new(4, 2)], Some(Color::Purple));
+        assert_eq!(matrix[Coordinate::new(2, 0)], None);
+        assert_eq!(matrix[Coordinate::new(9, 1)], Some(Color::Cyan));
+
+        let printed = matrix.to_string();
+        assert_eq!(printed.lines().count(), Matrix::HEIGHT);
+        assert!(printed.ends_with("....T.....\n...TTT...I\nZZ.OO.XXXI"));
+        assert_eq!(printed.parse(), Ok(matrix));
+    }
+
+    #[test]
+    fn engine_marks_the_falling_piece() {
+        let mut engine = Engine::with_matrix("XXXX..XXXX".parse().unwrap());
+        let o = PieceSet::standard().kind("O").unwrap();
+        engine.DEBUG_test_cursor_location(o, o.spawn());
+
+        let (matrix, cursor) = Matrix::parse_with_cursor(&engine.to_string()).unwrap();
+        assert_eq!(matrix, engine.matrix);
+        let (mut cells, _) = engine.cursor_info().unwrap();
+        cells.sort_by_key(|coord| (coord.y, coord.x));
+        assert_eq!(cursor, cells);
+        assert!(engine.to_string().parse::<Matrix>().is_err());
+    }
+
+    #[test]
+    fn bad_text_is_rejected() {
+        for text in [
+            "XXXX",
+            "XXXXXXXXXXX",
+            "XXXX?XXXXX",
+            &"..........\n".repeat(21),
+        ] {
+            assert!(text.parse::<Matrix>().is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// The title, pause and game over screens, worked the same way in every
+// frontend. While a menu is up the arrow keys, D-pad and left stick move
+// about it whatever the bindings say.
+
+use super::Command;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Nav {
+    Up,
+    Down,
+    Select,
+    Back,
+}
+
+// What picking an item does
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Choice {
+    // the title screen's modes, by their place in the list
+    Play(usize),
+    HighScores,
+    // a mode's high scores, by its place in that list
+    Table(usize),
+    // done typing in the name for a new record
+    Save,
+    Resume,
+    Retry,
+    Title,
+    Quit,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Menu {
+    pub heading: String,
+    // between the heading and the items, such as how the game went
+    pub lines: Vec<String>,
+    items: Vec<(String, Choice)>,
+    selected: usize,
+    // what backing out does
+    back: Choice,
+}
+
+impl Menu {
+    pub fn new(heading: impl ToString, items: Vec<(String, Choice)>, back: Choice) -> Self {
+        Self {
+            heading: heading.to_string(),
+            lines: Vec::new(),
+            items,
+            selected: 0,
+            back,
+        }
+    }
+
+    pub fn with_lines(self, lines: Vec<String>) -> Self {
+        Self { lines, ..self }
+    }
+
+    // Everything shown, top to bottom, with gaps between the heading, the
+    // lines and the items
+    pub fn text(&self) -> Vec<String> {
+        let mut text = vec![self.heading.clone(), String::new()];
+        if !self.lines.is_empty() {
+            text.extend(self.lines.iter().cloned());
+            text.push(String::new());
+        }
+        text.extend(self.item_lines());
+        text
+    }
+
+    // The selected one is pointed out
+    fn item_lines(&self) -> Vec<String> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(index, (label, _))| match index == self.selected {
+                true => format!("> {} <", label),
+                false => label.clone(),
+            })
+            .collect()
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    // Moves the selection, round from one end to the other, or returns
+    // what was picked
+    pub fn nav(&mut self, nav: Nav) -> Option<Choice> {
+        let count = self.items.len().max(1);
+        match nav {
+            Nav::Up => self.selected = (self.selected + count - 1) % count,
+            Nav::Down => self.selected = (self.selected + 1) % count,
+            Nav::Select => return self.items.get(self.selected).map(|&(_, choice)| choice),
+            Nav::Back => return Some(self.back),
+        }
+        None
+    }
+}
+
+// Keys named as in the config
+pub fn key_nav(name: &str) -> Option<Nav> {
+    Some(match name {
+        "Up" => Nav::Up,
+        "Down" => Nav::Down,
+        "Return" | "Space" => Nav::Select,
+        "Escape" | "Backspace" => Nav::Back,
+        _ => return None,
+    })
+}
+
+// Keys named as in the config, while typing in a name: letters, digits and
+// spaces are typed, and the menu keys still work
+pub fn key_typed(name: &str) -> Option<Command> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next(), name) {
+        (Some(char), None, _) if char.is_ascii_alphanumeric() => {
+            Some(Command::Type(char.to_ascii_uppercase()))
+        }
+        (_, _, "Space") => Some(Command::Type(' ')),
+        (_, _, "Backspace") => Some(Command::Erase),
+        _ => key_nav(name).map(Command::Menu),
+    }
+}
+
+// Buttons and stick directions named as in the config
+pub fn button_nav(name: &str) -> Option<Nav> {
+    Some(match name {
+        "dpup" | "-lefty" => Nav::Up,
+        "dpdown" | "+lefty" => Nav::Down,
+        "a" | "start" => Nav::Select,
+        "b" | "back" => Nav::Back,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn selection_wraps() {
+        let items = vec![
+            ("Resume".to_owned(), Choice::Resume),
+            ("Quit".to_owned(), Choice::Quit),
+        ];
+        let mut menu = Menu::new("Paused", items, Choice::Resume);
+        assert_eq!(menu.nav(Nav::Up), None);
+        assert_eq!(menu.selected(), 1);
+        assert_eq!(menu.item_lines(), ["Resume", "> Quit <"]);
+        assert_eq!(menu.nav(Nav::Down), None);
+        assert_eq!(menu.nav(Nav::Select), Some(Choice::Resume));
+        assert_eq!(menu.nav(Nav::Back), Some(Choice::Resume));
+    }
+
+    #[test]
+    fn names_are_typed() {
+        let typed: Vec<_> = ["a", "7", "Space", "Backspace", "Return", "Left", "Tab"]
+            .iter()
+            .map(|name| key_typed(name))
+            .collect();
+        assert_eq!(
+            typed,
+            [
+                Some(Command::Type('A')),
+                Some(Command::Type('7')),
+                Some(Command::Type(' ')),
+                Some(Command::Erase),
+                Some(Command::Menu(Nav::Select)),
+                None,
+                None,
+            ]
+        );
+    }
+}
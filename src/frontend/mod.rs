@@ -0,0 +1,822 @@
+// The single player game loop, written once for every frontend. A frontend
+// supplies an `InputSource` for what the player pressed, a `Clock` to pace
+// frames and a `Renderer` to show the game; tests drive it with scripted
+// input and a clock that steps by a fixed amount. Around the game are the
+// title, pause and game over screens, each a `Menu` drawn over whatever game
+// there is, and the game's clock only runs while it is being played. A game
+// that makes its mode's high score table asks for a name first.
+
+use std::{
+    rc::Rc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bot::{Bot, Driver},
+    config::Handling,
+    engine::{Engine, Input, MoveKind},
+    mode::{Game, Outcome},
+    scores::{Entry, Scores, NAME_LENGTH},
+};
+
+use menu::{Choice, Menu, Nav};
+
+pub mod menu;
+
+// What the player asked for since the last frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    // a key tapped, from sources that cannot tell when it is let go
+    Input(Input),
+    // a key going down and coming back up, so it can be held
+    Press(Input),
+    Release(Input),
+    // hand the controls to the bot and back
+    ToggleBot,
+    // pass the board on as a fumen
+    Share,
+    NextTheme,
+    // pauses a game, or carries on with a paused one
+    Pause,
+    // the player has turned to something else, so the game waits for them
+    FocusLost,
+    Menu(Nav),
+    // typing in a name
+    Type(char),
+    Erase,
+    Quit,
+}
+
+// What the keys and buttons are for at the moment
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Focus {
+    Game,
+    // moving about a menu rather than playing
+    Menu,
+    // typing in a name, with the menu keys still working
+    Text,
+}
+
+pub trait InputSource {
+    fn poll(&mut self, focus: Focus) -> Vec<Command>;
+}
+
+pub trait Clock {
+    // Waits for the next frame if need be, and returns the time since the last
+    fn tick(&mut self) -> Duration;
+}
+
+pub trait Renderer {
+    // Err ends the game, e.g. when the output has gone away
+    fn draw(&mut self, frame: &Frame) -> Result<(), ()>;
+
+    // Hands `fumen` to the player, such as by putting it on the clipboard
+    fn share(&mut self, _fumen: &str) {}
+
+    fn next_theme(&mut self) {}
+}
+
+// Starts a game afresh, each time its mode is picked or played again
+pub type NewGame = Rc<dyn Fn() -> Game>;
+
+// What there is to show this frame
+pub struct Frame<'app> {
+    // the game being played, paused or over; none on the title screen
+    pub game: Option<&'app Game>,
+    pub driving: bool,
+    // over the game, if any
+    pub menu: Option<&'app Menu>,
+}
+
+enum Screen {
+    Title(Menu),
+    Playing,
+    Paused(Menu),
+    // a new record, waiting for a name
+    Naming(Menu, Naming),
+    Over(Menu),
+    // the modes to see the high scores of, then one mode's table
+    HighScores(Menu),
+    Table(Menu),
+}
+
+// A record set by the game just over
+struct Naming {
+    mode: &'static str,
+    rank: usize,
+    entry: Entry,
+}
+
+impl Naming {
+    // If `game` made its mode's table, with the name filled in as `name`
+    fn new(game: &Game, scores: &Scores, name: &str) -> Option<Self> {
+        let record = game.record()?;
+        Some(Self {
+            mode: game.name(),
+            rank: scores.rank(game.name(), record)?,
+            entry: Entry {
+                name: name.to_owned(),
+                record,
+            },
+        })
+    }
+
+    fn lines(&self) -> Vec<String> {
+        vec![
+            self.mode.to_owned(),
+            format!("#{}  {}", self.rank + 1, self.entry.record),
+            String::new(),
+            format!("Name: {}_", self.entry.name),
+        ]
+    }
+
+    fn edit(&mut self, command: Command) {
+        let name = &mut self.entry.name;
+        match command {
+            Command::Type(char) if name.chars().count() < NAME_LENGTH => name.push(char),
+            Command::Erase => {
+                name.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Screen {
+    fn title(modes: &[(&'static str, NewGame)]) -> Self {
+        let mut items: Vec<_> = modes
+            .iter()
+            .enumerate()
+            .map(|(index, (name, _))| (name.to_string(), Choice::Play(index)))
+            .collect();
+        items.push(("High scores".to_owned(), Choice::HighScores));
+        items.push(("Quit".to_owned(), Choice::Quit));
+        Screen::Title(Menu::new("Tetris", items, Choice::Quit))
+    }
+
+    fn paused() -> Self {
+        let items = [
+            ("Resume", Choice::Resume),
+            ("Retry", Choice::Retry),
+            ("Title", Choice::Title),
+            ("Quit", Choice::Quit),
+        ];
+        let items = items.map(|(label, choice)| (label.to_owned(), choice));
+        Screen::Paused(Menu::new("Paused", items.into(), Choice::Resume))
+    }
+
+    fn over(game: &Game) -> Self {
+        let heading = match game.outcome() {
+            Some(Outcome::Completed) => "Complete!",
+            _ => "Game over",
+        };
+        let results = game.results().into_iter();
+        let lines = results.map(|stat| format!("{}: {}", stat.label, stat.value));
+        let items = [
+            ("Retry", Choice::Retry),
+            ("Title", Choice::Title),
+            ("Quit", Choice::Quit),
+        ];
+        let items = items.map(|(label, choice)| (label.to_owned(), choice));
+        Screen::Over(Menu::new(heading, items.into(), Choice::Title).with_lines(lines.collect()))
+    }
+
+    fn naming(naming: Naming) -> Self {
+        let items = vec![("Save".to_owned(), Choice::Save)];
+        let menu = Menu::new("New record!", items, Choice::Save).with_lines(naming.lines());
+        Screen::Naming(menu, naming)
+    }
+
+    fn high_scores(modes: &[String]) -> Self {
+        let mut items: Vec<_> = modes
+            .iter()
+            .enumerate()
+            .map(|(index, mode)| (mode.clone(), Choice::Table(index)))
+            .collect();
+        items.push(("Back".to_owned(), Choice::Title));
+        Screen::HighScores(Menu::new("High scores", items, Choice::Title))
+    }
+
+    fn table(mode: &str, scores: &Scores) -> Self {
+        let mut lines: Vec<_> = scores
+            .table(mode)
+            .iter()
+            .enumerate()
+            .map(|(rank, entry)| format!("{:>2} {:<8} {}", rank + 1, entry.name, entry.record))
+            .collect();
+        if lines.is_empty() {
+            lines.push("No records yet".to_owned());
+        }
+        let items = vec![("Back".to_owned(), Choice::HighScores)];
+        Screen::Table(Menu::new(mode, items, Choice::HighScores).with_lines(lines))
+    }
+
+    fn focus(&self) -> Focus {
+        match self {
+            Screen::Playing => Focus::Game,
+            Screen::Naming(..) => Focus::Text,
+            _ => Focus::Menu,
+        }
+    }
+
+    fn menu(&self) -> Option<&Menu> {
+        match self {
+            Screen::Title(menu)
+            | Screen::Paused(menu)
+            | Screen::Naming(menu, _)
+            | Screen::Over(menu)
+            | Screen::HighScores(menu)
+            | Screen::Table(menu) => Some(menu),
+            Screen::Playing => None,
+        }
+    }
+
+    fn menu_mut(&mut self) -> Option<&mut Menu> {
+        match self {
+            Screen::Title(menu)
+            | Screen::Paused(menu)
+            | Screen::Naming(menu, _)
+            | Screen::Over(menu)
+            | Screen::HighScores(menu)
+            | Screen::Table(menu) => Some(menu),
+            Screen::Playing => None,
+        }
+    }
+}
+
+// The title screen's modes, then any others that have saved scores
+fn score_modes(modes: &[(&'static str, NewGame)], scores: &Scores) -> Vec<String> {
+    let mut names: Vec<_> = modes.iter().map(|(name, _)| name.to_string()).collect();
+    for mode in scores.modes() {
+        if !names.iter().any(|name| name == mode) {
+            names.push(mode.to_owned());
+        }
+    }
+    names
+}
+
+// Wall clock time, optionally sleeping so frames are at least `frame` apart
+pub struct FrameClock {
+    frame: Duration,
+    last: Instant,
+}
+
+impl FrameClock {
+    pub fn new(frame: Duration) -> Self {
+        Self {
+            frame,
+            last: Instant::now(),
+        }
+    }
+
+    // For frontends that are already paced, such as by vsync
+    pub fn unpaced() -> Self {
+        Self::new(Duration::ZERO)
+    }
+}
+
+impl Clock for FrameClock {
+    fn tick(&mut self) -> Duration {
+        thread::sleep(self.frame.saturating_sub(self.last.elapsed()));
+        let now = Instant::now();
+        let delta = now - self.last;
+        self.last = now;
+        delta
+    }
+}
+
+// Repeats the moves and soft drops being held down. A repeated move is not
+// another input as far as finesse is concerned.
+pub struct AutoRepeat {
+    handling: Handling,
+    // the latest pressed last, as it wins
+    held: Vec<MoveKind>,
+    // until the held move next repeats
+    shift_delay: Duration,
+    // since the last soft drop, while one is held
+    soft_drop_time: Option<Duration>,
+}
+
+impl AutoRepeat {
+    pub fn new(handling: Handling) -> Self {
+        Self {
+            handling,
+            held: Vec::new(),
+            shift_delay: Duration::ZERO,
+            soft_drop_time: None,
+        }
+    }
+
+    pub fn press(&mut self, input: Input) {
+        match input {
+            Input::Move(kind) => {
+                self.held.retain(|&held| held != kind);
+                self.held.push(kind);
+                self.shift_delay = self.handling.das;
+            }
+            Input::SoftDrop => self.soft_drop_time = Some(Duration::ZERO),
+            _ => {}
+        }
+    }
+
+    pub fn release(&mut self, input: Input) {
+        match input {
+            Input::Move(kind) => {
+                self.held.retain(|&held| held != kind);
+                // the other direction, if still held, charges afresh
+                self.shift_delay = self.handling.das;
+            }
+            Input::SoftDrop => self.soft_drop_time = None,
+            _ => {}
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        self.held.clear();
+        self.soft_drop_time = None;
+    }
+
+    // Plays the repeats due in the `delta` since the last update
+    pub fn update(&mut self, engine: &mut Engine, delta: Duration) {
+        let drop_time = engine.drop_time();
+        self.play(delta, drop_time, |input| match input {
+            Input::Move(kind) => engine.move_cursor(kind).is_ok(),
+            _ => engine.soft_drop().is_ok(),
+        });
+    }
+
+    // As `update`, for a game that takes its inputs some other way: `repeat`
+    // plays each move or soft drop, and says whether there was room for it.
+    // A row falls in `drop_time` without soft dropping.
+    pub fn play(
+        &mut self,
+        delta: Duration,
+        drop_time: Duration,
+        mut repeat: impl FnMut(Input) -> bool,
+    ) {
+        if let Some(&kind) = self.held.last() {
+            let mut time = delta;
+            while time >= self.shift_delay {
+                time -= self.shift_delay;
+                if self.handling.arr.is_zero() {
+                    while repeat(Input::Move(kind)) {}
+                    self.shift_delay = Duration::ZERO;
+                    time = Duration::ZERO;
+                    break;
+                }
+                let _ = repeat(Input::Move(kind));
+                self.shift_delay = self.handling.arr;
+            }
+            self.shift_delay -= time;
+        }
+
+        if let Some(time) = self.soft_drop_time.as_mut() {
+            let interval = drop_time / self.handling.soft_drop_factor;
+            *time += delta;
+            while *time >= interval {
+                // on the floor, so don't save up drops for the next piece
+                if !repeat(Input::SoftDrop) {
+                    *time = Duration::ZERO;
+                    break;
+                }
+                *time -= interval;
+            }
+        }
+    }
+}
+
+// Opens on the title screen offering `modes` by name, or goes straight
+// into `first` if there is one, and runs until the player quits. With a
+// `bot` it starts at the controls; without one, the built-in bot steps in
+// when asked. Records go into `scores`, unless the bot had a hand in them.
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    modes: Vec<(&'static str, NewGame)>,
+    first: Option<NewGame>,
+    mut bot: Option<Driver>,
+    mut scores: Scores,
+    handling: Handling,
+    input: &mut impl InputSource,
+    clock: &mut impl Clock,
+    renderer: &mut impl Renderer,
+) {
+    let mut driving = bot.is_some();
+    // whether the bot has played any of the current game
+    let mut assisted = false;
+    // the last name a record was saved under
+    let mut name = String::new();
+    let mut repeat = AutoRepeat::new(handling);
+    let mut game = first.as_ref().map(|new_game| new_game());
+    // what retrying starts
+    let mut current = first;
+    let mut screen = match game {
+        Some(_) => Screen::Playing,
+        None => Screen::title(&modes),
+    };
+    loop {
+        for command in input.poll(screen.focus()) {
+            let playing = matches!(screen, Screen::Playing);
+            let choice = match command {
+                Command::Quit => return,
+                Command::Menu(nav) => screen.menu_mut().and_then(|menu| menu.nav(nav)),
+                Command::Pause | Command::FocusLost if playing => {
+                    repeat.release_all();
+                    screen = Screen::paused();
+                    None
+                }
+                Command::Pause if matches!(screen, Screen::Paused(_)) => Some(Choice::Resume),
+                Command::ToggleBot if playing => {
+                    driving = !driving;
+                    bot.get_or_insert_with(|| Driver::Heuristic(Bot::new()))
+                        .reset();
+                    repeat.release_all();
+                    None
+                }
+                Command::Share => {
+                    match game.as_ref().map(|game| game.engine().to_fumen()) {
+                        Some(Ok(fumen)) => renderer.share(&fumen),
+                        Some(Err(error)) => eprintln!("{}", error),
+                        None => {}
+                    }
+                    None
+                }
+                Command::NextTheme => {
+                    renderer.next_theme();
+                    None
+                }
+                Command::Type(_) | Command::Erase => {
+                    if let Screen::Naming(menu, naming) = &mut screen {
+                        naming.edit(command);
+                        menu.lines = naming.lines();
+                    }
+                    None
+                }
+                Command::Release(input) => {
+                    repeat.release(input);
+                    None
+                }
+                Command::Input(input) | Command::Press(input) if playing && !driving => {
+                    if let Some(engine) = game.as_mut().and_then(Game::engine_mut) {
+                        engine.input(input);
+                    }
+                    if let Command::Press(input) = command {
+                        repeat.press(input);
+                    }
+                    None
+                }
+                _ => None,
+            };
+
+            match choice {
+                Some(Choice::Quit) => return,
+                Some(Choice::Resume) => screen = Screen::Playing,
+                Some(Choice::Title) => {
+                    game = None;
+                    screen = Screen::title(&modes);
+                }
+                Some(Choice::Play(index)) => {
+                    current = Some(modes[index].1.clone());
+                    game = current.as_ref().map(|new_game| new_game());
+                    assisted = false;
+                    if let Some(bot) = bot.as_mut() {
+                        bot.reset();
+                    }
+                    screen = Screen::Playing;
+                }
+                Some(Choice::Retry) => {
+                    game = current.as_ref().map(|new_game| new_game());
+                    assisted = false;
+                    if let Some(bot) = bot.as_mut() {
+                        bot.reset();
+                    }
+                    screen = Screen::Playing;
+                }
+                Some(Choice::HighScores) => {
+                    screen = Screen::high_scores(&score_modes(&modes, &scores));
+                }
+                Some(Choice::Table(index)) => {
+                    screen = Screen::table(&score_modes(&modes, &scores)[index], &scores);
+                }
+                Some(Choice::Save) => {
+                    if let Screen::Naming(_, naming) = &screen {
+                        let mut entry = naming.entry.clone();
+                        name = entry.name.trim().to_owned();
+                        entry.name = match name.is_empty() {
+                            true => "???".to_owned(),
+                            false => name.clone(),
+                        };
+                        scores.insert(naming.mode, entry);
+                        if let Err(error) = scores.save() {
+                            eprintln!("{}", error);
+                        }
+                    }
+                    if let Some(game) = &game {
+                        screen = Screen::over(game);
+                    }
+                }
+                None => {}
+            }
+        }
+
+        // the game stands still while a menu is up
+        let delta = clock.tick();
+        let playing = matches!(screen, Screen::Playing);
+        if let Some(game) = game.as_mut().filter(|_| playing) {
+            if let Some(engine) = game.engine_mut() {
+                match (driving, bot.as_mut()) {
+                    (true, Some(bot)) => {
+                        for input in bot.poll(engine, delta) {
+                            engine.input(input);
+                        }
+                    }
+                    _ => repeat.update(engine, delta),
+                }
+            }
+            assisted |= driving;
+            game.update(delta);
+            if game.outcome().is_some() {
+                repeat.release_all();
+                screen = match Naming::new(game, &scores, &name).filter(|_| !assisted) {
+                    Some(naming) => Screen::naming(naming),
+                    None => Screen::over(game),
+                };
+            }
+        }
+
+        let frame = Frame {
+            game: game.as_ref(),
+            driving,
+            menu: screen.menu(),
+        };
+        if renderer.draw(&frame).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        mode::{Marathon, Sprint},
+    };
+
+    struct Script(VecDeque<Vec<Command>>);
+
+    impl InputSource for Script {
+        fn poll(&mut self, _focus: Focus) -> Vec<Command> {
+            self.0.pop_front().unwrap_or(vec![Command::Quit])
+        }
+    }
+
+    // Drops pieces straight down until the game is over, then follows the
+    // script through the menus
+    struct TopOut(VecDeque<Vec<Command>>);
+
+    impl InputSource for TopOut {
+        fn poll(&mut self, focus: Focus) -> Vec<Command> {
+            match focus {
+                Focus::Game => vec![Command::Input(Input::HardDrop)],
+                _ => self.0.pop_front().unwrap_or(vec![Command::Quit]),
+            }
+        }
+    }
+
+    struct Steps(Duration);
+
+    impl Clock for Steps {
+        fn tick(&mut self) -> Duration {
+            self.0
+        }
+    }
+
+    // Each frame's pieces placed, whether the bot was driving, the game's
+    // clock and the menu's heading, and the lines of the last menu shown
+    #[derive(Default)]
+    struct Record {
+        frames: Vec<(u32, bool, Duration, Option<String>)>,
+        lines: Vec<String>,
+        shared: Vec<String>,
+    }
+
+    impl Renderer for Record {
+        fn draw(&mut self, frame: &Frame) -> Result<(), ()> {
+            let game = frame.game;
+            self.frames.push((
+                game.map_or(0, |game| game.engine().pieces_placed()),
+                frame.driving,
+                game.map_or(Duration::ZERO, Game::clock),
+                frame.menu.map(|menu| menu.heading.clone()),
+            ));
+            if let Some(menu) = frame.menu {
+                self.lines = menu.lines.clone();
+            }
+            Ok(())
+        }
+
+        fn share(&mut self, fumen: &str) {
+            self.shared.push(fumen.to_owned());
+        }
+    }
+
+    #[test]
+    fn loop_plays_until_quit() {
+        // through the countdown, then a piece dropped by hand
+        let mut script = vec![vec![]; 4];
+        script.push(vec![Command::Input(Input::HardDrop), Command::Share]);
+        script.push(vec![Command::ToggleBot]);
+        let mut input = Script(script.into());
+        let mut renderer = Record::default();
+
+        let sprint: NewGame = Rc::new(|| Game::new(Engine::with_seed(1), Sprint::new()));
+        run(
+            Vec::new(),
+            Some(sprint),
+            None,
+            Scores::default(),
+            Handling::default(),
+            &mut input,
+            &mut Steps(Game::COUNTDOWN / 3),
+            &mut renderer,
+        );
+
+        assert_eq!(renderer.frames.len(), 6);
+        assert_eq!(renderer.frames[4].0, 1);
+        assert!(!renderer.frames[4].1);
+        assert!(renderer.frames[5].1);
+        assert_eq!(renderer.shared.len(), 1);
+        assert!(renderer.shared[0].starts_with("v115@"));
+    }
+
+    #[test]
+    fn pausing_stops_the_clock() {
+        let step = Duration::from_secs(1);
+        let sprint: NewGame = Rc::new(|| Game::new(Engine::with_seed(1), Sprint::new()));
+        let modes = vec![("Sprint", sprint)];
+        // through the countdown and a second of play before pausing
+        let mut script = vec![vec![], vec![Command::Menu(Nav::Select)]];
+        script.extend([vec![], vec![], vec![], vec![Command::FocusLost], vec![]]);
+        script.push(vec![Command::Pause]);
+        script.push(vec![Command::Pause, Command::Menu(Nav::Down)]);
+        script.push(vec![Command::Menu(Nav::Select)]);
+        let mut renderer = Record::default();
+        run(
+            modes,
+            None,
+            None,
+            Scores::default(),
+            Handling::default(),
+            &mut Script(script.into()),
+            &mut Steps(step),
+            &mut renderer,
+        );
+
+        let clocks: Vec<_> = renderer.frames.iter().map(|frame| frame.2).collect();
+        let headings: Vec<_> = renderer
+            .frames
+            .iter()
+            .map(|frame| frame.3.as_deref())
+            .collect();
+        assert_eq!(headings[..2], [Some("Tetris"), None]);
+        assert_eq!(clocks[3..9], [0, 1, 1, 1, 2, 2].map(|secs| step * secs));
+        assert_eq!(
+            headings[5..9],
+            [Some("Paused"), Some("Paused"), None, Some("Paused")]
+        );
+        // retried from the pause menu
+        assert_eq!(headings[9], None);
+        assert_eq!(clocks[9], Duration::ZERO);
+    }
+
+    #[test]
+    fn records_are_named() {
+        let marathon: NewGame = Rc::new(|| Game::new(Engine::with_seed(1), Marathon::new(1)));
+        let modes = vec![("Marathon", marathon.clone())];
+        let script = [
+            vec![Command::Type('A'), Command::Type('B')],
+            vec![Command::Erase, Command::Type('C')],
+            vec![Command::Menu(Nav::Select)],
+            vec![Command::Menu(Nav::Back)],
+            vec![Command::Menu(Nav::Down)],
+            vec![Command::Menu(Nav::Select)],
+            vec![Command::Menu(Nav::Select)],
+        ];
+        let mut renderer = Record::default();
+        run(
+            modes,
+            Some(marathon),
+            None,
+            Scores::default(),
+            Handling::default(),
+            &mut TopOut(script.into()),
+            &mut Steps(Game::COUNTDOWN),
+            &mut renderer,
+        );
+
+        let mut headings: Vec<_> = renderer
+            .frames
+            .iter()
+            .filter_map(|frame| frame.3.as_deref())
+            .collect();
+        headings.dedup();
+        assert_eq!(
+            headings,
+            [
+                "New record!",
+                "Game over",
+                "Tetris",
+                "High scores",
+                "Marathon"
+            ]
+        );
+        assert!(renderer.lines[0].starts_with(" 1 AC       "));
+    }
+
+    // Every frame's engine
+    struct Engines(Vec<Engine>);
+
+    impl Renderer for Engines {
+        fn draw(&mut self, frame: &Frame) -> Result<(), ()> {
+            self.0.extend(frame.game.map(|game| game.engine().clone()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn bot_starts_afresh_when_handed_back() {
+        let step = Duration::from_millis(60);
+        let countdown = (Game::COUNTDOWN.as_millis() / step.as_millis()) as usize + 1;
+        // the bot is part way through placing the first piece when the
+        // player takes over, drops it and hands the controls back
+        let mut script = vec![vec![]; countdown + 2];
+        script.push(vec![Command::ToggleBot]);
+        script.push(vec![Command::Input(Input::HardDrop)]);
+        script.push(vec![Command::ToggleBot]);
+        script.extend(vec![vec![]; 40]);
+        let sprint: NewGame = Rc::new(|| Game::new(Engine::with_seed(1), Sprint::new()));
+        let mut renderer = Engines(Vec::new());
+        run(
+            Vec::new(),
+            Some(sprint),
+            Some(Driver::Heuristic(Bot::new())),
+            Scores::default(),
+            Handling::default(),
+            &mut Script(script.into()),
+            &mut Steps(step),
+            &mut renderer,
+        );
+
+        let placed = |count| {
+            let engines = renderer.0.iter();
+            engines
+                .clone()
+                .find(|engine| engine.pieces_placed() == count)
+        };
+        let handed_back = placed(1).unwrap();
+        assert!(handed_back.cursor_info().is_some());
+        let mut expected = handed_back.clone();
+        for input in Bot::new().best_placement(&expected) {
+            expected.input(input);
+        }
+        let played = placed(2).unwrap();
+        assert!(played.cells().eq(expected.cells()));
+    }
+
+    #[test]
+    fn held_moves_repeat() {
+        let millis = Duration::from_millis;
+        let column = |engine: &Engine| {
+            let (cells, _) = engine.cursor_info().unwrap();
+            cells.iter().map(|cell| cell.x).min().unwrap()
+        };
+        let mut handling = Config::default().handling;
+        handling.das = millis(100);
+        handling.arr = millis(20);
+        let mut repeat = AutoRepeat::new(handling);
+        let mut engine = Engine::with_seed(1);
+        engine.update(Duration::ZERO);
+        let start = column(&engine);
+
+        repeat.press(Input::Move(MoveKind::Right));
+        repeat.update(&mut engine, millis(99));
+        assert_eq!(column(&engine), start);
+        repeat.update(&mut engine, millis(1));
+        assert_eq!(column(&engine), start + 1);
+        repeat.update(&mut engine, millis(40));
+        assert_eq!(column(&engine), start + 3);
+
+        repeat.release(Input::Move(MoveKind::Right));
+        repeat.update(&mut engine, millis(200));
+        assert_eq!(column(&engine), start + 3);
+
+        handling.arr = Duration::ZERO;
+        let mut repeat = AutoRepeat::new(handling);
+        repeat.press(Input::Move(MoveKind::Left));
+        repeat.update(&mut engine, millis(100));
+        assert_eq!(column(&engine), 0);
+    }
+}
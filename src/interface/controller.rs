@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+
+use sdl2::{
+    controller::{Axis, GameController},
+    event::Event,
+    GameControllerSubsystem,
+};
+
+use crate::{
+    config::Bindings,
+    frontend::{menu::button_nav, Command},
+};
+
+use super::{held, pressed};
+
+// The game controllers plugged in, opened as SDL reports them, including
+// those there at the start. Stick directions and triggers are read as
+// buttons that are down while past the dead zone.
+pub struct Controllers {
+    subsystem: GameControllerSubsystem,
+    // each in the first seat free when it was plugged in, so unplugging one
+    // does not move the others
+    seats: Vec<Option<GameController>>,
+    // the stick directions and triggers down, by controller and name
+    pushed: HashSet<(u32, String)>,
+    dead_zone: i16,
+}
+
+impl Controllers {
+    // `dead_zone` is a fraction of the way to the end of a stick or trigger
+    pub fn new(subsystem: GameControllerSubsystem, dead_zone: f32) -> Self {
+        Self {
+            subsystem,
+            seats: Vec::new(),
+            pushed: HashSet::new(),
+            dead_zone: (dead_zone * i16::MAX as f32) as i16,
+        }
+    }
+
+    // What `event` means for the game, or the `menu` if one is up, if it
+    // comes from a controller, along with the seat of the controller
+    pub fn event(
+        &mut self,
+        event: &Event,
+        buttons: &Bindings,
+        menu: bool,
+    ) -> Vec<(usize, Command)> {
+        let button =
+            |name: &str, down| pressed(button_nav(name), buttons.command(name), down, menu);
+        let (which, commands): (u32, Vec<_>) = match *event {
+            Event::ControllerDeviceAdded { which, .. } => {
+                match self.subsystem.open(which) {
+                    Ok(controller) => match self.seats.iter().position(Option::is_none) {
+                        Some(seat) => self.seats[seat] = Some(controller),
+                        None => self.seats.push(Some(controller)),
+                    },
+                    Err(error) => eprintln!("Failed to open a game controller: {}", error),
+                }
+                return Vec::new();
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                // let go of whatever its sticks were holding down
+                let (gone, kept) = self.pushed.drain().partition(|(id, _)| *id == which);
+                self.pushed = kept;
+                let released = gone
+                    .into_iter()
+                    .filter_map(|(_, name)| held(buttons.command(&name), false));
+                (which, released.collect())
+            }
+            Event::ControllerButtonDown {
+                which,
+                button: down,
+                ..
+            } => (which, button(&down.string(), true).into_iter().collect()),
+            Event::ControllerButtonUp {
+                which, button: up, ..
+            } => (which, button(&up.string(), false).into_iter().collect()),
+            Event::ControllerAxisMotion {
+                which, axis, value, ..
+            } => {
+                let mut commands = Vec::new();
+                for (name, down) in directions(axis, value, self.dead_zone) {
+                    let key = (which, name);
+                    if down == self.pushed.contains(&key) {
+                        continue;
+                    }
+                    commands.extend(button(&key.1, down));
+                    if down {
+                        self.pushed.insert(key);
+                    } else {
+                        self.pushed.remove(&key);
+                    }
+                }
+                (which, commands)
+            }
+            _ => return Vec::new(),
+        };
+
+        let seat = self.seats.iter().position(|controller| {
+            controller
+                .as_ref()
+                .is_some_and(|controller| controller.instance_id() == which)
+        });
+        let seat = match seat {
+            Some(seat) => seat,
+            None => return Vec::new(),
+        };
+        if let Event::ControllerDeviceRemoved { .. } = event {
+            self.seats[seat] = None;
+        }
+        commands
+            .into_iter()
+            .map(|command| (seat, command))
+            .collect()
+    }
+}
+
+// The buttons `axis` stands in for, named as in the config, and whether each
+// is down. Sticks go both ways, with y pointing down; triggers only one.
+fn directions(axis: Axis, value: i16, dead_zone: i16) -> Vec<(String, bool)> {
+    let name = axis.string();
+    match axis {
+        Axis::TriggerLeft | Axis::TriggerRight => vec![(name, value > dead_zone)],
+        _ => vec![
+            (format!("-{}", name), value < -dead_zone),
+            (format!("+{}", name), value > dead_zone),
+        ],
+    }
+}
@@ -0,0 +1,204 @@
+use std::time::{Duration, Instant};
+
+use cgmath::Point2;
+use sdl2::rect::Rect;
+
+use crate::engine::{Color, Engine, Lock, Matrix};
+
+const LOCK_FLASH: Duration = Duration::from_millis(150);
+// cleared rows blink for a while, then the rows above fall into place
+const CLEAR_FLASH: Duration = Duration::from_millis(200);
+const BLINK: Duration = Duration::from_millis(50);
+const COLLAPSE: Duration = Duration::from_millis(120);
+const SHAKE: Duration = Duration::from_millis(300);
+// as a fraction of the viewport's height
+const SHAKE_AMPLITUDE: f32 = 0.015;
+const TETRIS: usize = 4;
+
+pub type Row = [Option<Color>; Matrix::WIDTH];
+
+// Animations for the latest piece to lock, played by the clock while the
+// game carries on. The engine has already cleared the rows, so they only
+// change where and how the board is drawn.
+pub struct Effects {
+    // the engine's count when its latest lock was picked up
+    placed: u32,
+    lock: Option<Lock>,
+    started: Instant,
+    now: Instant,
+}
+
+impl Effects {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            placed: 0,
+            lock: None,
+            started: now,
+            now,
+        }
+    }
+
+    // Picks up any piece locked since the last frame, and moves the
+    // animations on to now
+    pub fn update(&mut self, engine: &Engine) {
+        self.now = Instant::now();
+        if engine.pieces_placed() != self.placed {
+            self.placed = engine.pieces_placed();
+            self.lock = engine.last_lock().cloned();
+            self.started = self.now;
+        }
+    }
+
+    // Since the latest lock was picked up, as of the last update; the
+    // animations below are given it
+    pub fn elapsed(&self) -> Duration {
+        self.now - self.started
+    }
+
+    // The placed cells that are left, where they are now, and how bright
+    // their flash is from 1 down to 0. Cleared rows take cells with them and
+    // let those above down; garbage pushes them up.
+    pub fn lock_flash(&self, elapsed: Duration) -> Option<(Vec<Point2<usize>>, f32)> {
+        let lock = self.lock.as_ref()?;
+        let left = LOCK_FLASH.checked_sub(elapsed)?;
+        let cells = lock
+            .cells
+            .iter()
+            .filter(|cell| lock.cleared.iter().all(|&(y, _)| y != cell.y))
+            .map(|cell| {
+                let below = lock.cleared.iter().filter(|&&(y, _)| y < cell.y).count();
+                Point2::new(cell.x, cell.y - below + lock.garbage)
+            })
+            .filter(|cell| cell.y < Matrix::HEIGHT)
+            .collect();
+        Some((cells, left.as_secs_f32() / LOCK_FLASH.as_secs_f32()))
+    }
+
+    // The cleared rows while they blink, and whether they are lit up
+    pub fn clearing(&self, elapsed: Duration) -> Option<(&[(usize, Row)], bool)> {
+        let lock = self.lock.as_ref()?;
+        if lock.cleared.is_empty() || elapsed >= CLEAR_FLASH {
+            return None;
+        }
+        let lit = (elapsed.as_millis() / BLINK.as_millis()).is_multiple_of(2);
+        Some((&lock.cleared, lit))
+    }
+
+    // How many rows higher to draw the stack's row `y`: at first where it
+    // was before the clear, then falling to where it is now
+    pub fn lift(&self, y: usize, elapsed: Duration) -> f32 {
+        let lock = match &self.lock {
+            Some(lock) => lock,
+            None => return 0.0,
+        };
+        let mut before = y;
+        for &(cleared, _) in &lock.cleared {
+            if cleared <= before {
+                before += 1;
+            }
+        }
+        let fallen = elapsed.saturating_sub(CLEAR_FLASH).as_secs_f32() / COLLAPSE.as_secs_f32();
+        (before - y) as f32 * (1.0 - fallen.min(1.0))
+    }
+
+    // `viewport` shaken after a tetris, less and less until it settles
+    pub fn shaken(&self, viewport: Rect) -> Rect {
+        let tetris = self
+            .lock
+            .as_ref()
+            .is_some_and(|lock| lock.cleared.len() >= TETRIS);
+        let left = match SHAKE.checked_sub(self.elapsed()) {
+            Some(left) if tetris => left.as_secs_f32() / SHAKE.as_secs_f32(),
+            _ => return viewport,
+        };
+        let amplitude = SHAKE_AMPLITUDE * viewport.height() as f32 * left;
+        let time = self.elapsed().as_secs_f32();
+        let mut shaken = viewport;
+        shaken.offset(
+            ((time * 90.0).sin() * amplitude) as i32,
+            ((time * 70.0).cos() * amplitude) as i32,
+        );
+        shaken
+    }
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cgmath::Vector2;
+
+    use super::*;
+    use crate::engine::piece_set::PieceSet;
+
+    fn locked(cells: &[(usize, usize)], cleared: &[usize]) -> Effects {
+        let row = [Some(Color::Red); Matrix::WIDTH];
+        let mut effects = Effects::new();
+        effects.lock = Some(Lock {
+            cells: cells.iter().map(|&cell| Point2::from(cell)).collect(),
+            cleared: cleared.iter().map(|&y| (y, row)).collect(),
+            garbage: 0,
+        });
+        effects
+    }
+
+    #[test]
+    fn flash_fades_on_what_is_left() {
+        // an L whose bottom two rows are cleared
+        let effects = locked(&[(0, 1), (0, 2), (0, 3), (1, 3)], &[1, 2]);
+        let (cells, strength) = effects.lock_flash(Duration::ZERO).unwrap();
+        assert_eq!(cells, [Point2::new(0, 1), Point2::new(1, 1)]);
+        assert_eq!(strength, 1.0);
+
+        let (_, strength) = effects.lock_flash(LOCK_FLASH / 2).unwrap();
+        assert!((strength - 0.5).abs() < 1e-6);
+        assert_eq!(effects.lock_flash(LOCK_FLASH * 2), None);
+    }
+
+    #[test]
+    fn flash_rises_with_garbage() {
+        let mut engine = Engine::with_matrix(Matrix::blank());
+        let o = PieceSet::standard().kind("O").unwrap();
+        engine.DEBUG_test_cursor_location(o, Vector2::new(3, 10));
+        engine.receive_garbage(2);
+        engine.hard_drop();
+        assert_eq!(engine.last_lock().unwrap().garbage, 2);
+
+        let mut effects = Effects::new();
+        effects.update(&engine);
+        let (cells, _) = effects.lock_flash(Duration::ZERO).unwrap();
+        let placed: Vec<_> = engine
+            .cells()
+            .filter(|&(_, cell)| cell == Some(o.color()))
+            .map(|(coord, _)| coord)
+            .collect();
+        assert_eq!(cells, placed);
+        assert!(cells.iter().all(|cell| cell.y >= 2));
+    }
+
+    #[test]
+    fn cleared_rows_blink_then_fall() {
+        let effects = locked(&[(9, 0), (9, 1), (9, 2), (9, 3)], &[0, 2]);
+        let (rows, lit) = effects.clearing(Duration::ZERO).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(lit);
+        assert!(!effects.clearing(BLINK).unwrap().1);
+        assert_eq!(effects.clearing(CLEAR_FLASH), None);
+
+        // row 1 was row 3 before the clear, and row 0 was row 1
+        let lift = |y, elapsed| effects.lift(y, elapsed);
+        assert_eq!(lift(1, Duration::ZERO), 2.0);
+        assert_eq!(lift(0, CLEAR_FLASH), 1.0);
+        assert!((lift(1, CLEAR_FLASH + COLLAPSE / 2) - 1.0).abs() < 1e-6);
+        assert_eq!(lift(1, CLEAR_FLASH + COLLAPSE), 0.0);
+
+        let untouched = locked(&[(9, 0)], &[]);
+        assert_eq!(untouched.clearing(Duration::ZERO), None);
+        assert_eq!(untouched.lift(5, Duration::ZERO), 0.0);
+    }
+}
@@ -0,0 +1,459 @@
+use cgmath::{Point2, Vector2};
+use sdl2::{pixels::Color, rect::Rect, render::Canvas, video::Window};
+
+// Letters are 5 x 7 font pixels, with one pixel between letters and two
+// between lines; the drawn size is a whole multiple of that
+const GLYPH: Vector2<u32> = Vector2::new(5, 7);
+const ADVANCE: u32 = GLYPH.x + 1;
+const LINE: u32 = GLYPH.y + 2;
+
+// Each glyph's rows top first, `#` for a pixel drawn. Lower case letters
+// borrow the upper case ones; anything missing comes out as `?`.
+const GLYPHS: [(char, [&str; 7]); 54] = [
+    (
+        ' ',
+        [
+            ".....", ".....", ".....", ".....", ".....", ".....", ".....",
+        ],
+    ),
+    (
+        '0',
+        [
+            ".###.", "#...#", "#..##", "#.#.#", "##..#", "#...#", ".###.",
+        ],
+    ),
+    (
+        '1',
+        [
+            "..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###.",
+        ],
+    ),
+    (
+        '2',
+        [
+            ".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####",
+        ],
+    ),
+    (
+        '3',
+        [
+            "#####", "...#.", "..#..", "...#.", "....#", "#...#", ".###.",
+        ],
+    ),
+    (
+        '4',
+        [
+            "...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#.",
+        ],
+    ),
+    (
+        '5',
+        [
+            "#####", "#....", "####.", "....#", "....#", "#...#", ".###.",
+        ],
+    ),
+    (
+        '6',
+        [
+            "..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###.",
+        ],
+    ),
+    (
+        '7',
+        [
+            "#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#...",
+        ],
+    ),
+    (
+        '8',
+        [
+            ".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###.",
+        ],
+    ),
+    (
+        '9',
+        [
+            ".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##..",
+        ],
+    ),
+    (
+        'A',
+        [
+            ".###.", "#...#", "#...#", "#####", "#...#", "#...#", "#...#",
+        ],
+    ),
+    (
+        'B',
+        [
+            "####.", "#...#", "#...#", "####.", "#...#", "#...#", "####.",
+        ],
+    ),
+    (
+        'C',
+        [
+            ".###.", "#...#", "#....", "#....", "#....", "#...#", ".###.",
+        ],
+    ),
+    (
+        'D',
+        [
+            "###..", "#..#.", "#...#", "#...#", "#...#", "#..#.", "###..",
+        ],
+    ),
+    (
+        'E',
+        [
+            "#####", "#....", "#....", "####.", "#....", "#....", "#####",
+        ],
+    ),
+    (
+        'F',
+        [
+            "#####", "#....", "#....", "####.", "#....", "#....", "#....",
+        ],
+    ),
+    (
+        'G',
+        [
+            ".###.", "#...#", "#....", "#.###", "#...#", "#...#", ".####",
+        ],
+    ),
+    (
+        'H',
+        [
+            "#...#", "#...#", "#...#", "#####", "#...#", "#...#", "#...#",
+        ],
+    ),
+    (
+        'I',
+        [
+            ".###.", "..#..", "..#..", "..#..", "..#..", "..#..", ".###.",
+        ],
+    ),
+    (
+        'J',
+        [
+            "..###", "...#.", "...#.", "...#.", "...#.", "#..#.", ".##..",
+        ],
+    ),
+    (
+        'K',
+        [
+            "#...#", "#..#.", "#.#..", "##...", "#.#..", "#..#.", "#...#",
+        ],
+    ),
+    (
+        'L',
+        [
+            "#....", "#....", "#....", "#....", "#....", "#....", "#####",
+        ],
+    ),
+    (
+        'M',
+        [
+            "#...#", "##.##", "#.#.#", "#.#.#", "#...#", "#...#", "#...#",
+        ],
+    ),
+    (
+        'N',
+        [
+            "#...#", "#...#", "##..#", "#.#.#", "#..##", "#...#", "#...#",
+        ],
+    ),
+    (
+        'O',
+        [
+            ".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###.",
+        ],
+    ),
+    (
+        'P',
+        [
+            "####.", "#...#", "#...#", "####.", "#....", "#....", "#....",
+        ],
+    ),
+    (
+        'Q',
+        [
+            ".###.", "#...#", "#...#", "#...#", "#.#.#", "#..#.", ".##.#",
+        ],
+    ),
+    (
+        'R',
+        [
+            "####.", "#...#", "#...#", "####.", "#.#..", "#..#.", "#...#",
+        ],
+    ),
+    (
+        'S',
+        [
+            ".####", "#....", "#....", ".###.", "....#", "....#", "####.",
+        ],
+    ),
+    (
+        'T',
+        [
+            "#####", "..#..", "..#..", "..#..", "..#..", "..#..", "..#..",
+        ],
+    ),
+    (
+        'U',
+        [
+            "#...#", "#...#", "#...#", "#...#", "#...#", "#...#", ".###.",
+        ],
+    ),
+    (
+        'V',
+        [
+            "#...#", "#...#", "#...#", "#...#", "#...#", ".#.#.", "..#..",
+        ],
+    ),
+    (
+        'W',
+        [
+            "#...#", "#...#", "#...#", "#.#.#", "#.#.#", "#.#.#", ".#.#.",
+        ],
+    ),
+    (
+        'X',
+        [
+            "#...#", "#...#", ".#.#.", "..#..", ".#.#.", "#...#", "#...#",
+        ],
+    ),
+    (
+        'Y',
+        [
+            "#...#", "#...#", ".#.#.", "..#..", "..#..", "..#..", "..#..",
+        ],
+    ),
+    (
+        'Z',
+        [
+            "#####", "....#", "...#.", "..#..", ".#...", "#....", "#####",
+        ],
+    ),
+    (
+        '.',
+        [
+            ".....", ".....", ".....", ".....", ".....", ".##..", ".##..",
+        ],
+    ),
+    (
+        ',',
+        [
+            ".....", ".....", ".....", ".....", ".##..", "..#..", ".#...",
+        ],
+    ),
+    (
+        ':',
+        [
+            ".....", ".##..", ".##..", ".....", ".##..", ".##..", ".....",
+        ],
+    ),
+    (
+        '\'',
+        [
+            "..#..", "..#..", ".#...", ".....", ".....", ".....", ".....",
+        ],
+    ),
+    (
+        '!',
+        [
+            "..#..", "..#..", "..#..", "..#..", "..#..", ".....", "..#..",
+        ],
+    ),
+    (
+        '?',
+        [
+            ".###.", "#...#", "....#", "...#.", "..#..", ".....", "..#..",
+        ],
+    ),
+    (
+        '-',
+        [
+            ".....", ".....", ".....", "#####", ".....", ".....", ".....",
+        ],
+    ),
+    (
+        '+',
+        [
+            ".....", "..#..", "..#..", "#####", "..#..", "..#..", ".....",
+        ],
+    ),
+    (
+        '=',
+        [
+            ".....", ".....", "#####", ".....", "#####", ".....", ".....",
+        ],
+    ),
+    (
+        '_',
+        [
+            ".....", ".....", ".....", ".....", ".....", ".....", "#####",
+        ],
+    ),
+    (
+        '/',
+        [
+            ".....", "....#", "...#.", "..#..", ".#...", "#....", ".....",
+        ],
+    ),
+    (
+        '%',
+        [
+            "##...", "##..#", "...#.", "..#..", ".#...", "#..##", "...##",
+        ],
+    ),
+    (
+        '#',
+        [
+            ".#.#.", ".#.#.", "#####", ".#.#.", "#####", ".#.#.", ".#.#.",
+        ],
+    ),
+    (
+        '(',
+        [
+            "...#.", "..#..", ".#...", ".#...", ".#...", "..#..", "...#.",
+        ],
+    ),
+    (
+        ')',
+        [
+            ".#...", "..#..", "...#.", "...#.", "...#.", "..#..", ".#...",
+        ],
+    ),
+    (
+        '<',
+        [
+            "...#.", "..#..", ".#...", "#....", ".#...", "..#..", "...#.",
+        ],
+    ),
+    (
+        '>',
+        [
+            ".#...", "..#..", "...#.", "....#", "...#.", "..#..", ".#...",
+        ],
+    ),
+];
+
+fn glyph(letter: char) -> &'static [&'static str; 7] {
+    let letter = letter.to_ascii_uppercase();
+    let find = |letter| GLYPHS.iter().find(|(key, _)| *key == letter);
+    &find(letter).or_else(|| find('?')).unwrap().1
+}
+
+// In font pixels
+fn text_width(text: &str) -> u32 {
+    (text.chars().count() as u32 * ADVANCE).saturating_sub(1)
+}
+
+fn text_height(lines: usize) -> u32 {
+    (lines as u32 * LINE).saturating_sub(LINE - GLYPH.y)
+}
+
+// The largest scale at which `lines`, stacked, fit inside `area`; nothing
+// fits at zero
+fn fit(lines: &[String], area: Vector2<u32>) -> u32 {
+    let width = lines.iter().map(|line| text_width(line)).max().unwrap_or(0);
+    (area.x / width.max(1)).min(area.y / text_height(lines.len()).max(1))
+}
+
+pub fn draw_text(
+    canvas: &mut Canvas<Window>,
+    text: &str,
+    top_left: Point2<i32>,
+    scale: u32,
+    color: Color,
+) {
+    let mut pixels = Vec::new();
+    for (index, letter) in text.chars().enumerate() {
+        let left = top_left.x + (index as u32 * ADVANCE * scale) as i32;
+        for (y, row) in glyph(letter).iter().enumerate() {
+            for (x, _) in row.char_indices().filter(|&(_, pixel)| pixel == '#') {
+                let x = left + (x as u32 * scale) as i32;
+                let y = top_left.y + (y as u32 * scale) as i32;
+                pixels.push(Rect::new(x, y, scale, scale));
+            }
+        }
+    }
+    canvas.set_draw_color(color);
+    canvas.fill_rects(&pixels).unwrap();
+}
+
+// Stacks `lines` in `area`, each centred across it, as large as they fit
+// below `max_scale`; the block of text sits at the top, or the middle if
+// `centred`
+pub fn draw_lines(
+    canvas: &mut Canvas<Window>,
+    area: Rect,
+    lines: &[String],
+    max_scale: u32,
+    centred: bool,
+    color: Color,
+) {
+    let size = Vector2::new(area.width(), area.height());
+    let scale = fit(lines, size).min(max_scale);
+    if scale == 0 {
+        return;
+    }
+    let height = text_height(lines.len()) * scale;
+    let mut top = area.y();
+    if centred {
+        top += ((size.y - height) / 2) as i32;
+    }
+    for line in lines {
+        let left = area.x() + ((size.x - text_width(line) * scale) / 2) as i32;
+        draw_text(canvas, line, Point2::new(left, top), scale, color);
+        top += (LINE * scale) as i32;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|&line| line.to_owned()).collect()
+    }
+
+    #[test]
+    fn glyphs_are_five_pixels_wide() {
+        for (letter, rows) in GLYPHS {
+            for row in rows {
+                assert_eq!(row.len(), GLYPH.x as usize, "{:?}", letter);
+                assert!(row.chars().all(|pixel| pixel == '.' || pixel == '#'));
+            }
+            let first = GLYPHS.iter().position(|&(key, _)| key == letter);
+            let last = GLYPHS.iter().rposition(|&(key, _)| key == letter);
+            assert_eq!(first, last, "{:?} is drawn twice", letter);
+        }
+    }
+
+    #[test]
+    fn unknown_letters_are_question_marks() {
+        assert_eq!(glyph('~'), glyph('?'));
+        assert_eq!(glyph('é'), glyph('?'));
+        assert_eq!(glyph('a'), glyph('A'));
+        assert_ne!(glyph('A'), glyph('?'));
+    }
+
+    #[test]
+    fn text_is_measured_in_font_pixels() {
+        assert_eq!(text_width(""), 0);
+        assert_eq!(text_width("A"), 5);
+        assert_eq!(text_width("Level"), 29);
+        assert_eq!(text_height(0), 0);
+        assert_eq!(text_height(1), 7);
+        assert_eq!(text_height(3), 25);
+    }
+
+    #[test]
+    fn text_fits_its_area() {
+        // two letters take 11 x 7 font pixels
+        assert_eq!(fit(&lines(&["AB"]), Vector2::new(10, 7)), 0);
+        assert_eq!(fit(&lines(&["AB"]), Vector2::new(11, 7)), 1);
+        assert_eq!(fit(&lines(&["AB"]), Vector2::new(35, 100)), 3);
+        // the widest line sets the width and the line count the height
+        assert_eq!(fit(&lines(&["A", "ABC"]), Vector2::new(100, 33)), 2);
+    }
+}
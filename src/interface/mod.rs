@@ -1,202 +1,822 @@
-use std::time::Duration;
-
-use crate::engine::{Color as SemanticColor, Engine, Matrix, MoveKind};
+use std::time::{Duration, Instant};
+
+use crate::{
+    bot::Driver,
+    config::{
+        theme::{Pattern, Theme},
+        Bindings, Config, Visuals,
+    },
+    engine::{
+        piece::Kind as PieceKind, piece_set::PieceSet, CellIter, Color as SemanticColor, Engine,
+        Matrix,
+    },
+    frontend::{
+        self,
+        menu::{key_nav, key_typed, Menu, Nav},
+        AutoRepeat, Command, Focus, Frame, FrameClock, InputSource, NewGame, Renderer,
+    },
+    mode::{self, Game, Outcome, Standing, Stat, Versus},
+    scores::Scores,
+};
 use cgmath::{ElementWise, EuclideanSpace, Point2, Vector2};
-use render::ScreenColor;
+use controller::Controllers;
+use effects::Effects;
+use render::{sdl_color, ScreenColor};
 use sdl2::{
-    event::Event, keyboard::Keycode, pixels::Color, rect::Rect, render::Canvas, video::Window,
+    event::{Event, WindowEvent},
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+    EventPump, Sdl,
 };
 use sub_rect::SubRect;
 
 use self::sub_rect::Align;
 
+pub use network::{run_online, run_rollback};
+
+mod controller;
+mod effects;
+mod font;
+mod network;
 mod render;
 mod sub_rect;
 
 const INIT_SIZE: Vector2<u32> = Vector2::new(1024, 1024);
-const BACKGROUND_COLOR: Color = Color::RGB(0x10, 0x10, 0x18);
-const PLACEHOLDER_1: Color = Color::RGB(0x66, 0x77, 0x77);
-const PLACEHOLDER_2: Color = Color::RGB(0x66, 0x77, 0x77);
-
-struct Tick;
-struct LockdownTick;
-struct SoftDropTick;
-
-pub fn run(mut engine: Engine) {
+const QUEUE_SLOTS: u32 = 6;
+// the end screen dims the matrix towards the background
+const SHADE_ALPHA: u8 = 0xc0;
+const GHOST_ALPHA: u8 = 0x60;
+const FLASH_COLOR: Color = Color::RGB(0xff, 0xff, 0xff);
+const BANNER_COLOR: Color = Color::RGB(0xee, 0xee, 0xec);
+// so a short list of stats does not come out huge
+const MAX_TEXT_SCALE: u32 = 4;
+
+// Plays from the title screen, or straight into `first`, with the keys,
+// buttons, handling and visuals in `config`, keeping records in `scores`;
+// the bot binding hands the controls to `bot`, or the built-in bot if there
+// is none, and back
+pub fn run(
+    modes: Vec<(&'static str, NewGame)>,
+    first: Option<NewGame>,
+    bot: Option<Driver>,
+    scores: Scores,
+    config: &Config,
+) {
     let sdl = sdl2::init().expect("Failed to initialise SDL2");
-
-    let event_subsytem = sdl.event().expect("Failed to acquire event subsystem");
-    event_subsytem.register_custom_event::<Tick>().unwrap();
-    event_subsytem
-        .register_custom_event::<LockdownTick>()
-        .unwrap();
-    let mut canvas = {
-        let video = sdl.video().expect("Failed to acquire display");
-
-        let window = video
-            .window("Tetris", INIT_SIZE.x, INIT_SIZE.y)
-            .position_centered()
-            .resizable()
-            .build()
-            .expect("Failed to create window");
-        window
-            .into_canvas()
-            .accelerated()
-            .present_vsync()
-            .build()
-            .expect(" Failed to get render canvas")
+    let mut window = WindowRenderer {
+        canvas: open_canvas(&sdl),
+        title: String::new(),
+        visuals: config.visuals.clone(),
+        effects: Effects::new(),
     };
+    let mut controls = Controls::new(&sdl, config, vec![config.keys.clone()]);
+    frontend::run(
+        modes,
+        first,
+        bot,
+        scores,
+        config.handling,
+        &mut controls,
+        &mut FrameClock::unpaced(),
+        &mut window,
+    );
+}
 
-    let mut events = sdl.event_pump().expect("Failed to get event loop");
+// The keyboard and any game controllers. Game inputs are held down so they
+// repeat with the player's handling, rather than at the system's key repeat
+// rate. The window going out of focus pauses the game.
+struct Controls {
+    events: EventPump,
+    // by player, the first for a single player game
+    keys: Vec<Bindings>,
+    buttons: Bindings,
+    controllers: Option<Controllers>,
+}
 
-    event_subsytem.push_custom_event(Tick).unwrap();
-    event_subsytem.push_custom_event(LockdownTick).unwrap();
+impl Controls {
+    // One player for each set of `keys`
+    fn new(sdl: &Sdl, config: &Config, keys: Vec<Bindings>) -> Self {
+        // without controller support there is still the keyboard
+        let controllers = sdl.game_controller().ok();
+        Self {
+            events: sdl.event_pump().expect("Failed to get event loop"),
+            keys,
+            buttons: config.buttons.clone(),
+            controllers: controllers
+                .map(|subsystem| Controllers::new(subsystem, config.handling.dead_zone)),
+        }
+    }
 
-    let mut dirty: bool = true;
-    let mut lock_down = false;
-    loop {
-        for event in events.poll_iter() {
+    // Each command along with the player it is for. Only player one moves
+    // about menus and types. Alone, they can use any controller; otherwise
+    // the first plugged in is theirs, the second player two's and so on.
+    fn poll_players(&mut self, focus: Focus) -> Vec<(usize, Command)> {
+        let mut commands = Vec::new();
+        let menu = focus != Focus::Game;
+        let key = |key: Keycode, down| {
+            let name = key.name();
+            let mut commands = Vec::new();
+            for (player, keys) in self.keys.iter().enumerate() {
+                let command = match (focus, player) {
+                    (Focus::Text, 0) => key_typed(&name).filter(|_| down),
+                    (_, 0) => pressed(key_nav(&name), keys.command(&name), down, menu),
+                    (Focus::Game, _) => held(keys.command(&name), down),
+                    _ => None,
+                };
+                commands.extend(command.map(|command| (player, command)));
+            }
+            commands
+        };
+        for event in self.events.poll_iter() {
             match event {
-                Event::Quit { .. } => return,
-                Event::User { .. } if event.as_user_event_type::<Tick>().is_some() => {
-                    println!("Found tick event");
-                    dirty = true;
-                }
-                Event::User { .. } if event.as_user_event_type::<LockdownTick>().is_some() => {
-                    println!("Found lockdown event");
-                    dirty = true;
-                }
+                Event::Quit { .. } => commands.push((0, Command::Quit)),
+                Event::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } => commands.push((0, Command::FocusLost)),
                 Event::KeyDown {
-                    keycode: Some(key), ..
-                } => {
-                    if let Ok(input) = Input::try_from(key) {
-                        match input {
-                            Input::Move(kind) => drop(engine.move_cursor(kind)),
-                            Input::HardDrop => engine.hard_drop(),
-                            Input::SoftDrop => todo!("Soft drop ticks"),
-                        }
-                        dirty = true;
+                    keycode: Some(code),
+                    repeat: false,
+                    ..
+                } => commands.extend(key(code, true)),
+                Event::KeyUp {
+                    keycode: Some(code),
+                    ..
+                } => commands.extend(key(code, false)),
+                event => {
+                    if let Some(controllers) = self.controllers.as_mut() {
+                        let players = self.keys.len();
+                        let buttons = controllers.event(&event, &self.buttons, menu);
+                        commands.extend(buttons.into_iter().filter_map(|(seat, command)| {
+                            match players {
+                                1 => Some((0, command)),
+                                _ => (seat < players).then_some((seat, command)),
+                            }
+                        }));
                     }
                 }
-                _ => {}
             }
         }
+        commands
+    }
+}
 
-        if lock_down {}
-        if dirty {
-            draw(&mut canvas, &engine);
-            dirty = false;
+impl InputSource for Controls {
+    fn poll(&mut self, focus: Focus) -> Vec<Command> {
+        let commands = self.poll_players(focus).into_iter();
+        commands.map(|(_, command)| command).collect()
+    }
+}
+
+// A game input going down or up on `engine`, if it is being played, with
+// `repeat` keeping track of what is held
+fn play(command: Command, engine: Option<&mut Engine>, repeat: &mut AutoRepeat) {
+    match command {
+        Command::Press(input) => {
+            if let Some(engine) = engine {
+                engine.input(input);
+            }
+            repeat.press(input);
         }
+        Command::Release(input) => repeat.release(input),
+        // the keys that were down may come up unseen
+        Command::FocusLost => repeat.release_all(),
+        _ => {}
     }
 }
 
-enum Input {
-    Move(MoveKind),
-    SoftDrop,
-    HardDrop,
+// A key or button going down or up: game inputs are held, anything else
+// happens on the way down
+fn held(command: Option<Command>, down: bool) -> Option<Command> {
+    match (command?, down) {
+        (Command::Input(input), true) => Some(Command::Press(input)),
+        (Command::Input(input), false) => Some(Command::Release(input)),
+        (command, true) => Some(command),
+        (_, false) => None,
+    }
+}
+
+// As `held`, except that with a `menu` up whatever moves about it does just
+// that
+fn pressed(nav: Option<Nav>, command: Option<Command>, down: bool, menu: bool) -> Option<Command> {
+    match nav {
+        Some(nav) if menu => down.then_some(Command::Menu(nav)),
+        _ => held(command, down),
+    }
 }
 
-impl TryFrom<Keycode> for Input {
-    type Error = ();
+// Vsync paces the window, so it runs on an unpaced clock
+struct WindowRenderer {
+    canvas: Canvas<Window>,
+    title: String,
+    visuals: Visuals,
+    effects: Effects,
+}
+
+impl Renderer for WindowRenderer {
+    fn draw(&mut self, frame: &Frame) -> Result<(), ()> {
+        let mut status = frame.game.map_or(String::from("Tetris"), status_line);
+        if frame.driving && frame.game.is_some() {
+            status += " | Bot playing";
+        }
+        set_title(&mut self.canvas, &mut self.title, status);
+
+        self.canvas
+            .set_draw_color(sdl_color(self.visuals.theme.background));
+        self.canvas.clear();
+        let viewport = self.canvas.viewport();
+        if let Some(game) = frame.game {
+            self.effects.update(game.engine());
+            let effects = Some(&self.effects);
+            draw(&mut self.canvas, game, viewport, &self.visuals, effects);
+        }
+        if let Some(menu) = frame.menu {
+            draw_menu(&mut self.canvas, menu, viewport, &self.visuals.theme);
+        }
+        self.canvas.present();
+        Ok(())
+    }
+
+    // Printed as well, for when the clipboard is not to hand
+    fn share(&mut self, fumen: &str) {
+        println!("{}", fumen);
+        let _ = self
+            .canvas
+            .window()
+            .subsystem()
+            .clipboard()
+            .set_clipboard_text(fumen);
+    }
 
-    fn try_from(key: Keycode) -> Result<Self, Self::Error> {
-        Ok(match key {
-            Keycode::Right => Self::Move(MoveKind::Right),
-            Keycode::Left => Self::Move(MoveKind::Left),
-            Keycode::Up => Self::HardDrop,
-            Keycode::Down => Self::SoftDrop,
-            _ => return Err(()),
-        })
+    fn next_theme(&mut self) {
+        self.visuals.next_theme();
     }
 }
 
-fn draw(canvas: &mut Canvas<sdl2::video::Window>, engine: &Engine) {
-    canvas.set_draw_color(BACKGROUND_COLOR);
-    canvas.clear();
+// Both players share the keyboard, player two on the second set of keys
+// in `config`, and each can have a controller; both have its handling and
+// visuals
+pub fn run_versus(mut versus: Versus, config: &Config) {
+    let sdl = sdl2::init().expect("Failed to initialise SDL2");
+    let mut canvas = open_canvas(&sdl);
+    canvas
+        .window_mut()
+        .set_size(INIT_SIZE.x * 2, INIT_SIZE.y)
+        .unwrap();
+    let keys = vec![config.keys.clone(), config.keys2.clone()];
+    let mut controls = Controls::new(&sdl, config, keys);
+    let visuals = &config.visuals;
+
+    let mut repeats = [(); 2].map(|_| AutoRepeat::new(config.handling));
+    let mut last_frame = Instant::now();
+    let mut title = String::new();
+    let mut effects = [Effects::new(), Effects::new()];
+    loop {
+        for (player, command) in controls.poll_players(Focus::Game) {
+            match command {
+                Command::Quit => return,
+                Command::FocusLost => repeats.iter_mut().for_each(AutoRepeat::release_all),
+                command => {
+                    let engine = versus.player_mut(player).engine_mut();
+                    play(command, engine, &mut repeats[player]);
+                }
+            }
+        }
 
-    let viewport = canvas.viewport();
-    let ui_square = SubRect::absolute(viewport, (1.0, 1.0), None);
+        let now = Instant::now();
+        let delta = now - last_frame;
+        for (player, repeat) in repeats.iter_mut().enumerate() {
+            if let Some(engine) = versus.player_mut(player).engine_mut() {
+                repeat.update(engine, delta);
+            }
+        }
+        versus.update(delta);
+        last_frame = now;
 
-    let matrix = ui_square
-        .sub_rect((0.5, 1.0), None)
-        .sub_rect((7.0 / 8.0, 7.0 / 8.0), None);
+        set_title(&mut canvas, &mut title, versus_status_line(&versus));
 
-    let up_next = ui_square
-        .sub_rect((0.25, 0.25), Some((Align::Far, Align::Near)))
-        .sub_rect((0.75, 0.75), None);
+        canvas.set_draw_color(sdl_color(visuals.theme.background));
+        canvas.clear();
+        let sides = halves(canvas.viewport());
+        for ((player, side), effects) in versus.players().iter().zip(sides).zip(&mut effects) {
+            effects.update(player.engine());
+            draw(&mut canvas, player, side, visuals, Some(effects));
+        }
+        canvas.present();
+    }
+}
+
+fn open_canvas(sdl: &Sdl) -> Canvas<Window> {
+    let video = sdl.video().expect("Failed to acquire display");
+
+    let window = video
+        .window("Tetris", INIT_SIZE.x, INIT_SIZE.y)
+        .position_centered()
+        .resizable()
+        .build()
+        .expect("Failed to create window");
+    window
+        .into_canvas()
+        .accelerated()
+        .present_vsync()
+        .build()
+        .expect(" Failed to get render canvas")
+}
 
-    let hold = ui_square
-        .sub_rect((0.25, 0.25), Some((Align::Near, Align::Near)))
-        .sub_rect((0.75, 0.75), None);
+// Left and right halves of the window, one per player
+fn halves(viewport: Rect) -> [Rect; 2] {
+    let half_width = viewport.width() / 2;
+    [0, 1].map(|index| {
+        Rect::new(
+            viewport.x() + (half_width * index) as i32,
+            viewport.y(),
+            half_width,
+            viewport.height(),
+        )
+    })
+}
 
-    let queue = ui_square
-        .sub_rect((0.25, 0.75), Some((Align::Far, Align::Far)))
-        .sub_rect((5.0 / 8.0, 23.0 / 24.0), Some((Align::Center, Align::Near)));
+fn set_title(canvas: &mut Canvas<Window>, title: &mut String, new_title: String) {
+    if *title != new_title {
+        canvas.window_mut().set_title(&new_title).unwrap();
+        *title = new_title;
+    }
+}
 
-    let score = ui_square
-        .sub_rect((0.25, 11.0 / 16.0), Some((Align::Near, Align::Far)))
-        .sub_rect((7.0 / 8.0, 8.0 / 11.0), Some((Align::Center, Align::Near)));
+// The stats are drawn in the score panel; the title bar names the game
+fn status_line(game: &Game) -> String {
+    let mut line = format!("Tetris - {}", game.name());
+    if let Some(seconds) = game.countdown() {
+        line += &format!(" | {}", seconds);
+    }
+    match game.outcome() {
+        Some(Outcome::Completed) => line += " | Complete!",
+        Some(Outcome::ToppedOut) => line += " | Game over",
+        None => {}
+    }
+    line
+}
 
-    // Draw graphics
-    canvas.set_draw_color(PLACEHOLDER_1);
+// The mode's stats, or results once it is over, then whichever of the
+// usual ones it leaves out
+fn panel_stats(game: &Game) -> Vec<Stat> {
+    let engine = game.engine();
+    let mut stats = match game.outcome() {
+        Some(_) => game.results(),
+        None => game.stats(),
+    };
+    let usual = [
+        Stat::new("Score", engine.score().points),
+        Stat::new("Level", engine.level()),
+        Stat::new("Lines", engine.lines_cleared()),
+        Stat::new("Time", mode::format_time(game.clock())),
+        Stat::new("PPS", format!("{:.2}", mode::pps(engine, game.clock()))),
+    ];
+    for stat in usual {
+        if stats.iter().all(|shown| shown.label != stat.label) {
+            stats.push(stat);
+        }
+    }
+    stats
+}
 
-    for subrect in [&matrix, &up_next, &hold, &queue, &score] {
-        canvas.fill_rect(Rect::from(subrect)).unwrap();
+fn versus_status_line(versus: &Versus) -> String {
+    let mut line = String::from("Tetris - Versus");
+    if let Some(seconds) = versus.countdown() {
+        line += &format!(" | {}", seconds);
     }
+    match versus.standing() {
+        Standing::Playing => {
+            for (index, player) in versus.players().iter().enumerate() {
+                line += &format!(" | P{}", index + 1);
+                for stat in player.stats() {
+                    line += &format!(" {}: {}", stat.label, stat.value);
+                }
+            }
+        }
+        Standing::Won(winner) => line += &format!(" | Player {} wins!", winner + 1),
+        Standing::Draw => line += " | Draw",
+    }
+    line
+}
+
+// Draws one game into the `viewport` region of the canvas, shaken by any
+// `effects`
+fn draw(
+    canvas: &mut Canvas<sdl2::video::Window>,
+    game: &Game,
+    viewport: Rect,
+    visuals: &Visuals,
+    effects: Option<&Effects>,
+) {
+    let viewport = effects.map_or(viewport, |effects| effects.shaken(viewport));
+    let finished = game.outcome().is_some();
+    let (progress, stats) = (game.progress(), panel_stats(game));
+    draw_engine(
+        canvas,
+        game.engine(),
+        progress,
+        &stats,
+        finished,
+        viewport,
+        visuals,
+        effects,
+    );
+
+    // the countdown, then how it went, across the matrix
+    let banner = match (game.countdown(), game.outcome()) {
+        (Some(seconds), _) => seconds.to_string(),
+        (None, Some(Outcome::Completed)) => String::from("Complete!"),
+        (None, Some(Outcome::ToppedOut)) => String::from("Game over"),
+        (None, None) => return,
+    };
+    let area = Layout::new(viewport).matrix.sub_rect((0.8, 0.2), None);
+    font::draw_lines(
+        canvas,
+        Rect::from(area),
+        &[banner],
+        u32::MAX,
+        true,
+        BANNER_COLOR,
+    );
+}
 
+#[allow(clippy::too_many_arguments)]
+fn draw_engine(
+    canvas: &mut Canvas<Window>,
+    engine: &Engine,
+    progress: f32,
+    stats: &[Stat],
+    finished: bool,
+    viewport: Rect,
+    visuals: &Visuals,
+    effects: Option<&Effects>,
+) {
+    let theme = &visuals.theme;
+    let layout = Layout::new(viewport);
+    layout.draw_panels(canvas, progress, theme);
+    let lines: Vec<_> = stats
+        .iter()
+        .flat_map(|stat| [stat.label.to_owned(), stat.value.clone()])
+        .collect();
+    let score = layout.score.sub_rect((0.85, 0.9), None);
+    font::draw_lines(
+        canvas,
+        Rect::from(score),
+        &lines,
+        MAX_TEXT_SCALE,
+        false,
+        sdl_color(theme.text),
+    );
+
+    let ghost = engine.ghost_cells().filter(|_| visuals.ghost);
+    draw_cells(
+        canvas,
+        &layout.matrix,
+        theme,
+        engine.cells(),
+        engine.cursor_info(),
+        ghost,
+        effects,
+    );
+
+    // the first preview is up next, the rest queue up behind it
+    let slot = slot_size(engine.pieces());
+    let previews = || engine.queue().take(visuals.previews);
+    let held = engine.held().into_iter();
+    let (next_label, up_next) = labelled(&layout.up_next);
+    let (hold_label, hold) = labelled(&layout.hold);
+    let text_color = sdl_color(theme.text);
+    for (label, text) in [(next_label, "Next"), (hold_label, "Hold")] {
+        let text = [text.to_owned()];
+        font::draw_lines(canvas, Rect::from(label), &text, u32::MAX, true, text_color);
+    }
+    draw_pieces(canvas, &up_next, theme, slot, previews(), 1);
+    draw_pieces(canvas, &hold, theme, slot, held, 1);
+    let queue = previews().skip(1);
+    draw_pieces(canvas, &layout.queue, theme, slot, queue, QUEUE_SLOTS);
+
+    if finished {
+        shade(canvas, &layout.matrix, theme);
+    }
+}
+
+// The top of a piece panel for its label, and the rest for the piece
+fn labelled(panel: &SubRect) -> (SubRect, SubRect) {
+    let label = panel
+        .sub_rect((1.0, 0.25), Some((Align::Center, Align::Near)))
+        .sub_rect((0.8, 0.6), None);
+    let piece = panel.sub_rect((1.0, 0.75), Some((Align::Center, Align::Far)));
+    (label, piece)
+}
+
+// Draws a board only known from a snapshot, such as a remote opponent's
+fn draw_board(
+    canvas: &mut Canvas<Window>,
+    matrix: &Matrix,
+    finished: bool,
+    viewport: Rect,
+    theme: &Theme,
+) {
+    let layout = Layout::new(viewport);
+    layout.draw_panels(canvas, 0.0, theme);
+    draw_cells(
+        canvas,
+        &layout.matrix,
+        theme,
+        matrix.cells(),
+        None,
+        None,
+        None,
+    );
+    if finished {
+        shade(canvas, &layout.matrix, theme);
+    }
+}
+
+struct Layout {
+    matrix: SubRect,
+    up_next: SubRect,
+    hold: SubRect,
+    queue: SubRect,
+    score: SubRect,
+}
+
+impl Layout {
+    fn new(viewport: Rect) -> Self {
+        let ui_square = SubRect::absolute(viewport, (1.0, 1.0), None);
+
+        let matrix = ui_square
+            .sub_rect((0.5, 1.0), None)
+            .sub_rect((7.0 / 8.0, 7.0 / 8.0), None);
+
+        let up_next = ui_square
+            .sub_rect((0.25, 0.25), Some((Align::Far, Align::Near)))
+            .sub_rect((0.75, 0.75), None);
+
+        let hold = ui_square
+            .sub_rect((0.25, 0.25), Some((Align::Near, Align::Near)))
+            .sub_rect((0.75, 0.75), None);
+
+        let queue = ui_square
+            .sub_rect((0.25, 0.75), Some((Align::Far, Align::Far)))
+            .sub_rect((5.0 / 8.0, 23.0 / 24.0), Some((Align::Center, Align::Near)));
+
+        let score = ui_square
+            .sub_rect((0.25, 11.0 / 16.0), Some((Align::Near, Align::Far)))
+            .sub_rect((7.0 / 8.0, 8.0 / 11.0), Some((Align::Center, Align::Near)));
+
+        Self {
+            matrix,
+            up_next,
+            hold,
+            queue,
+            score,
+        }
+    }
+
+    fn draw_panels(&self, canvas: &mut Canvas<Window>, progress: f32, theme: &Theme) {
+        canvas.set_draw_color(sdl_color(theme.panel));
+        for subrect in [
+            &self.matrix,
+            &self.up_next,
+            &self.hold,
+            &self.queue,
+            &self.score,
+        ] {
+            canvas.fill_rect(Rect::from(subrect)).unwrap();
+        }
+
+        let progress = self
+            .score
+            .sub_rect((1.0, progress), Some((Align::Center, Align::Far)));
+        canvas.set_draw_color(sdl_color(theme.meter));
+        canvas.fill_rect(Rect::from(progress)).unwrap();
+    }
+}
+
+// The ghost, if any, takes the cursor's colour. Any `effects` move the
+// stack about and flash what has just locked and cleared.
+fn draw_cells(
+    canvas: &mut Canvas<Window>,
+    matrix: &SubRect,
+    theme: &Theme,
+    cells: CellIter<'_>,
+    cursor: Option<(Vec<Point2<usize>>, SemanticColor)>,
+    ghost: Option<Vec<Point2<usize>>>,
+    effects: Option<&Effects>,
+) {
     let mut cell_ctx = CellDrawContext {
         origin: matrix.bottom_left(),
         dims: matrix.size(),
+        grid: CellDrawContext::MATRIX_GRID,
         canvas,
+        theme,
     };
+    let elapsed = effects.map_or(Duration::ZERO, Effects::elapsed);
+    let lift = |y| effects.map_or(0.0, |effects| effects.lift(y, elapsed));
 
-    for (coord, cell) in engine.cells() {
-        cell_ctx.try_draw_cell(coord, cell);
+    for (coord, cell) in cells {
+        if let Some(color) = cell {
+            let rect = cell_ctx.lifted_rect(coord, lift(coord.y));
+            cell_ctx.fill_cell(rect, color);
+        }
     }
 
-    if let Some((cursor_cells, cursor_color)) = engine.cursor_info() {
+    if let Some((cells, strength)) = effects.and_then(|effects| effects.lock_flash(elapsed)) {
+        let alpha = (strength * u8::MAX as f32) as u8;
+        for coord in cells {
+            let rect = cell_ctx.lifted_rect(coord, lift(coord.y));
+            cell_ctx.blend(rect, FLASH_COLOR, alpha);
+        }
+    }
+    // the cleared rows stay where they were, blinking, until the rest fall
+    if let Some((rows, lit)) = effects.and_then(|effects| effects.clearing(elapsed)) {
+        for &(y, row) in rows {
+            for (x, cell) in row.into_iter().enumerate() {
+                let rect = cell_ctx.cell_rect(Point2::new(x, y));
+                match cell {
+                    Some(_) if lit => cell_ctx.blend(rect, FLASH_COLOR, u8::MAX),
+                    Some(color) => cell_ctx.fill_cell(rect, color),
+                    None => {}
+                }
+            }
+        }
+    }
+
+    if let Some((cursor_cells, cursor_color)) = cursor {
+        for coord in ghost.into_iter().flatten() {
+            cell_ctx.draw_ghost(coord, cursor_color);
+        }
         for coord in cursor_cells {
             cell_ctx.draw_cells(coord, cursor_color)
         }
     }
+}
 
-    canvas.present();
+// Room for any piece in `pieces`, plus a row between slots; never smaller
+// than the tetrominoes need so the panels keep their look
+fn slot_size(pieces: &'static PieceSet) -> Vector2<u32> {
+    let bounds = pieces
+        .kinds()
+        .map(bounding_box)
+        .fold(Vector2::new(4, 2), |slot, (_, size)| {
+            Vector2::new(slot.x.max(size.x), slot.y.max(size.y))
+        });
+    bounds + Vector2::new(0, 1)
+}
+
+// The lowest corner of `kind`'s cells and the size of the box around them
+fn bounding_box(kind: PieceKind) -> (Vector2<isize>, Vector2<u32>) {
+    let cells = kind.cells();
+    let min = cells.iter().fold(cells[0], |min, cell| {
+        Vector2::new(min.x.min(cell.x), min.y.min(cell.y))
+    });
+    let max = cells.iter().fold(cells[0], |max, cell| {
+        Vector2::new(max.x.max(cell.x), max.y.max(cell.y))
+    });
+    (min, (max - min + Vector2::new(1, 1)).cast().unwrap())
+}
+
+// Stacks pieces top to bottom in `panel`, each centred in a `slot` sized box
+// of square cells
+fn draw_pieces(
+    canvas: &mut Canvas<Window>,
+    panel: &SubRect,
+    theme: &Theme,
+    slot: Vector2<u32>,
+    pieces: impl Iterator<Item = PieceKind>,
+    slots: u32,
+) {
+    let grid = Vector2::new(slot.x, slot.y * slots);
+    let dims = panel.size().cast::<f32>().unwrap();
+    let cell = (dims.x / grid.x as f32).min(dims.y / grid.y as f32);
+    let panel = panel.sub_rect(
+        (cell * grid.x as f32 / dims.x, cell * grid.y as f32 / dims.y),
+        None,
+    );
+    let mut cell_ctx = CellDrawContext {
+        origin: panel.bottom_left(),
+        dims: panel.size(),
+        grid,
+        canvas,
+        theme,
+    };
+
+    for (index, kind) in (0..slots).rev().zip(pieces) {
+        let (min, size) = bounding_box(kind);
+        // the spare row at the bottom of each slot is the gap
+        let corner = Vector2::new(
+            (slot.x - size.x) / 2,
+            index * slot.y + 1 + (slot.y - 1 - size.y) / 2,
+        );
+        for cell in kind.cells() {
+            let coord = Point2::from_vec(corner.cast().unwrap() + (cell - min));
+            cell_ctx.draw_cells(coord.cast().unwrap(), kind.color());
+        }
+    }
+}
+
+// In place of the matrix, so a paused game cannot be studied
+fn draw_menu(canvas: &mut Canvas<Window>, menu: &Menu, viewport: Rect, theme: &Theme) {
+    let matrix = Layout::new(viewport).matrix;
+    canvas.set_draw_color(sdl_color(theme.background));
+    canvas.fill_rect(Rect::from(&matrix)).unwrap();
+    let area = matrix.sub_rect((0.9, 0.9), None);
+    font::draw_lines(
+        canvas,
+        Rect::from(area),
+        &menu.text(),
+        MAX_TEXT_SCALE,
+        true,
+        BANNER_COLOR,
+    );
+}
+
+// A finished board is dimmed, so the results in the stats panel and any
+// menu drawn over it stand out
+fn shade(canvas: &mut Canvas<Window>, matrix: &SubRect, theme: &Theme) {
+    let (r, g, b) = theme.background;
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(r, g, b, SHADE_ALPHA));
+    canvas.fill_rect(Rect::from(matrix)).unwrap();
+    canvas.set_blend_mode(BlendMode::None);
 }
 
 struct CellDrawContext<'canvas> {
     origin: Point2<i32>,
     dims: Vector2<u32>,
+    grid: Vector2<u32>,
     canvas: &'canvas mut Canvas<Window>,
+    theme: &'canvas Theme,
 }
 
 // '_ takes the same life time as CellDrawContext.canvas
 impl CellDrawContext<'_> {
-    const CELL_COUNT: Vector2<u32> = Vector2::new(Matrix::WIDTH as u32, Matrix::HEIGHT as u32);
+    const MATRIX_GRID: Vector2<u32> = Vector2::new(Matrix::WIDTH as u32, Matrix::HEIGHT as u32);
 
-    fn try_draw_cell(&mut self, coord: Point2<usize>, cell: Option<SemanticColor>) {
-        let cell = match cell {
-            Some(cell) => cell,
-            None => return,
-        };
-        self.draw_cells(coord, cell);
+    fn draw_cells(&mut self, coord: Point2<usize>, color: SemanticColor) {
+        self.fill_cell(self.cell_rect(coord), color);
     }
 
-    fn draw_cells(&mut self, coord: Point2<usize>, color: SemanticColor) {
+    // Patterns, if the theme has them, go over the cell in the background
+    // colour
+    fn fill_cell(&mut self, rect: Rect, color: SemanticColor) {
+        self.canvas.set_draw_color(self.theme.screen_color(color));
+        self.canvas.fill_rect(rect).unwrap();
+
+        let marks = self.theme.pattern(color).map_or(&[][..], Pattern::marks);
+        self.canvas.set_draw_color(sdl_color(self.theme.background));
+        for &(left, top, width, height) in marks {
+            let (w, h) = (rect.width() as f32, rect.height() as f32);
+            let mark = Rect::new(
+                rect.x() + (left * w) as i32,
+                rect.y() + (top * h) as i32,
+                ((width * w) as u32).max(1),
+                ((height * h) as u32).max(1),
+            );
+            self.canvas.fill_rect(mark).unwrap();
+        }
+    }
+
+    // A see-through cell, over whatever is beneath it
+    fn draw_ghost(&mut self, coord: Point2<usize>, color: SemanticColor) {
+        let color = self.theme.screen_color(color);
+        self.blend(self.cell_rect(coord), color, GHOST_ALPHA);
+    }
+
+    fn blend(&mut self, rect: Rect, Color { r, g, b, .. }: Color, alpha: u8) {
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(Color::RGBA(r, g, b, alpha));
+        self.canvas.fill_rect(rect).unwrap();
+        self.canvas.set_blend_mode(BlendMode::None);
+    }
+
+    // `coord`'s cell drawn `lift` cells higher, which need not be whole
+    fn lifted_rect(&self, coord: Point2<usize>, lift: f32) -> Rect {
+        let mut rect = self.cell_rect(coord);
+        let height = self.dims.y as f32 / self.grid.y as f32;
+        rect.offset(0, -(lift * height) as i32);
+        rect
+    }
+
+    fn cell_rect(&self, coord: Point2<usize>) -> Rect {
         let coord = coord.to_vec().cast::<u32>().unwrap();
         let this = (coord + Vector2::new(0, 1))
             .mul_element_wise(self.dims)
-            .div_element_wise(Self::CELL_COUNT);
+            .div_element_wise(self.grid);
         let next = (coord + Vector2::new(1, 0))
             .mul_element_wise(self.dims)
-            .div_element_wise(Self::CELL_COUNT);
+            .div_element_wise(self.grid);
 
-        let cell_rect = Rect::new(
+        Rect::new(
             self.origin.x + this.x as i32,
             self.origin.y - this.y as i32,
             next.x - this.x,
             this.y - next.y,
-        );
-        self.canvas.set_draw_color(color.screen_color());
-        self.canvas.fill_rect(cell_rect).unwrap();
+        )
     }
 }
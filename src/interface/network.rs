@@ -0,0 +1,256 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    config::Config,
+    engine::{Engine, Input, Matrix},
+    frontend::{AutoRepeat, Command, Focus, InputSource},
+    mode::{Battle, Game, Mode, Standing},
+    net::{
+        decode_board, encode_board,
+        link::Link,
+        rollback::{FrameInput, Handshake, Match, Session},
+        Connection, Message,
+    },
+};
+
+use super::{
+    draw, draw_board, draw_engine, effects::Effects, halves, open_canvas, play, render::sdl_color,
+    set_title, Controls, INIT_SIZE,
+};
+
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Online {
+    Waiting,
+    Playing,
+    Over { won: bool },
+    Disconnected,
+}
+
+pub fn run_online(address: &str, config: &Config) {
+    let mut connection = Connection::connect(address).expect("Failed to connect to server");
+
+    let sdl = sdl2::init().expect("Failed to initialise SDL2");
+    let mut canvas = open_canvas(&sdl);
+    canvas
+        .window_mut()
+        .set_size(INIT_SIZE.x * 2, INIT_SIZE.y)
+        .unwrap();
+    let mut controls = Controls::new(&sdl, config, vec![config.keys.clone()]);
+
+    let mut repeat = AutoRepeat::new(config.handling);
+    let mut state = Online::Waiting;
+    let mut game: Option<Game> = None;
+    let mut opponent = Matrix::blank();
+    let mut sent_board = String::new();
+    let mut sent_top_out = false;
+
+    let mut last_frame = Instant::now();
+    let mut last_snapshot = last_frame;
+    let mut title = String::new();
+    let mut effects = Effects::new();
+    loop {
+        for command in controls.poll(Focus::Game) {
+            match command {
+                Command::Quit => return,
+                command => play(
+                    command,
+                    game.as_mut().and_then(Game::engine_mut),
+                    &mut repeat,
+                ),
+            }
+        }
+
+        let messages = connection.poll().unwrap_or_else(|_| {
+            if matches!(state, Online::Waiting | Online::Playing) {
+                state = Online::Disconnected;
+            }
+            Vec::new()
+        });
+        for message in messages {
+            match message {
+                Message::Start => {
+                    game = Some(Game::new(Engine::new(), Battle));
+                    state = Online::Playing;
+                }
+                Message::Attack(lines) => {
+                    if let Some(engine) = game.as_mut().and_then(Game::engine_mut) {
+                        engine.receive_garbage(lines);
+                    }
+                }
+                Message::Board(cells) => opponent = decode_board(&cells).unwrap_or(opponent),
+                Message::End { won } => state = Online::Over { won },
+                Message::Waiting | Message::TopOut => {}
+            }
+        }
+
+        let now = Instant::now();
+        let delta = now - last_frame;
+        last_frame = now;
+
+        if let (Some(game), Online::Playing) = (game.as_mut(), state) {
+            if let Some(engine) = game.engine_mut() {
+                repeat.update(engine, delta);
+            }
+            game.update(delta);
+
+            let attack = game.engine_mut().map_or(0, Engine::take_attack);
+            if attack > 0 {
+                let _ = connection.send(&Message::Attack(attack));
+            }
+            if game.outcome().is_some() && !sent_top_out {
+                let _ = connection.send(&Message::TopOut);
+                sent_top_out = true;
+            }
+            if now - last_snapshot >= SNAPSHOT_INTERVAL {
+                let board = encode_board(game.engine());
+                if board != sent_board {
+                    let _ = connection.send(&Message::Board(board.clone()));
+                    sent_board = board;
+                }
+                last_snapshot = now;
+            }
+        }
+
+        let status = match state {
+            Online::Waiting => String::from("Waiting for an opponent"),
+            Online::Playing => match game.as_ref().and_then(Game::countdown) {
+                Some(seconds) => seconds.to_string(),
+                None => String::from("Fight!"),
+            },
+            Online::Over { won: true } => String::from("You win!"),
+            Online::Over { won: false } => String::from("You lose"),
+            Online::Disconnected => String::from("Disconnected"),
+        };
+        set_title(
+            &mut canvas,
+            &mut title,
+            format!("Tetris - Online | {}", status),
+        );
+
+        canvas.set_draw_color(sdl_color(config.visuals.theme.background));
+        canvas.clear();
+        let [left, right] = halves(canvas.viewport());
+        let visuals = &config.visuals;
+        match game.as_ref() {
+            Some(game) => {
+                effects.update(game.engine());
+                draw(&mut canvas, game, left, visuals, Some(&effects));
+            }
+            None => draw_board(&mut canvas, &Matrix::blank(), false, left, &visuals.theme),
+        }
+        let opponent_lost = state == Online::Over { won: true };
+        draw_board(&mut canvas, &opponent, opponent_lost, right, &visuals.theme);
+        canvas.present();
+    }
+}
+
+pub fn run_rollback(mut link: impl Link, config: &Config) {
+    let sdl = sdl2::init().expect("Failed to initialise SDL2");
+    let mut canvas = open_canvas(&sdl);
+    canvas
+        .window_mut()
+        .set_size(INIT_SIZE.x * 2, INIT_SIZE.y)
+        .unwrap();
+    let mut controls = Controls::new(&sdl, config, vec![config.keys.clone()]);
+
+    let mut repeat = AutoRepeat::new(config.handling);
+    let mut handshake = Handshake::new(rand::random());
+    let mut session: Option<Session> = None;
+    let mut pressed = FrameInput::default();
+    let mut unsimulated = Duration::ZERO;
+    // the peer stopped answering mid-match, which ends it
+    let mut disconnected = false;
+
+    let mut last_frame = Instant::now();
+    let mut title = String::new();
+    loop {
+        for command in controls.poll(Focus::Game) {
+            match command {
+                Command::Quit => return,
+                Command::Press(input) => {
+                    pressed.press(input);
+                    repeat.press(input);
+                }
+                command => play(command, None, &mut repeat),
+            }
+        }
+
+        let now = Instant::now();
+        let delta = now - last_frame;
+        last_frame = now;
+
+        let session = match &mut session {
+            Some(session) => session,
+            None => {
+                session = handshake.poll(&mut link);
+                set_title(
+                    &mut canvas,
+                    &mut title,
+                    String::from("Tetris - P2P | Waiting for peer"),
+                );
+                canvas.set_draw_color(sdl_color(config.visuals.theme.background));
+                canvas.clear();
+                canvas.present();
+                continue;
+            }
+        };
+
+        disconnected |= session.timed_out() && session.state().standing() == Standing::Playing;
+        if !disconnected {
+            session.sync(&mut link);
+            // while waiting on the peer the backlog is capped, so we do not race ahead after
+            unsimulated = (unsimulated + delta).min(Match::FRAME * Session::MAX_PREDICTION);
+            while unsimulated >= Match::FRAME && session.advance(pressed) {
+                pressed = FrameInput::default();
+                unsimulated -= Match::FRAME;
+                // what repeats over the frame just played goes in the next.
+                // A frame holds each input once, so a move to the wall takes
+                // a frame a step.
+                let engine = &session.state().engines()[session.local()];
+                repeat.play(Match::FRAME, engine.drop_time(), |input| {
+                    pressed.press(input);
+                    input == Input::SoftDrop
+                });
+            }
+        }
+
+        let state = session.state();
+        let local = session.local();
+        let status = match (state.countdown(), state.standing()) {
+            _ if disconnected => String::from("Peer disconnected"),
+            (Some(seconds), _) => seconds.to_string(),
+            (None, Standing::Playing) => String::from("Fight!"),
+            (None, Standing::Won(winner)) if winner == local => String::from("You win!"),
+            (None, Standing::Won(_)) => String::from("You lose"),
+            (None, Standing::Draw) => String::from("Draw"),
+        };
+        set_title(
+            &mut canvas,
+            &mut title,
+            format!("Tetris - P2P | {}", status),
+        );
+
+        canvas.set_draw_color(sdl_color(config.visuals.theme.background));
+        canvas.clear();
+        // the local player is always on the left
+        let sides = [local, 1 - local].map(|player| &state.engines()[player]);
+        for (engine, viewport) in sides.into_iter().zip(halves(canvas.viewport())) {
+            let progress = Battle.progress(engine, Duration::ZERO);
+            let stats = Battle.stats(engine, Duration::ZERO);
+            draw_engine(
+                &mut canvas,
+                engine,
+                progress,
+                &stats,
+                engine.is_topped_out() || disconnected,
+                viewport,
+                &config.visuals,
+                // a rollback would replay them
+                None,
+            );
+        }
+        canvas.present();
+    }
+}
@@ -0,0 +1,13 @@
+#![allow(dead_code)]
+// moves report failure as Err(()), the caller only needs to know it did not happen
+#![allow(clippy::result_unit_err)]
+pub mod bot;
+pub mod config;
+pub mod engine;
+pub mod frontend;
+pub mod interface;
+pub mod mode;
+pub mod net;
+pub mod scores;
+#[cfg(unix)]
+pub mod terminal;
@@ -1,15 +1,187 @@
-#![allow(dead_code)]
-#![feature(array_chunks)]
-use engine::{piece::Kind as PieceKind, Color, Engine, Matrix};
+use std::{rc::Rc, time::Duration};
+
+use tetris::{
+    bot::{Bot, Driver, ExternalBot},
+    config::Config,
+    engine::{fumen, piece_set::PieceSet, Engine},
+    frontend::NewGame,
+    interface,
+    mode::{Dig, Game, Marathon, Practice, Sprint, Ultra, Versus},
+    net::{
+        self,
+        link::{Simulated, UdpLink},
+    },
+    scores::Scores,
+};
 
-mod engine;
-mod interface;
 fn main() {
-    let mut matrix = Matrix::blank();
-    for col in 0..=6 {
-        matrix[(col, 0).into()] = Some(Color::Green);
+    let config = Config::load_or_create().unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1)
+    });
+    let mut args = std::env::args().skip(1).peekable();
+    // `tetris terminal ...` plays single player games in the terminal
+    let terminal = args.next_if(|arg| arg == "terminal").is_some();
+    // `tetris pieces <file> ...` plays with the pieces defined in `file`
+    let custom_pieces = args.peek().is_some_and(|arg| arg == "pieces");
+    let pieces = match args.next_if(|arg| arg == "pieces") {
+        Some(_) => {
+            let path = args.next().expect("Usage: tetris pieces <file> [mode]");
+            PieceSet::load(path).unwrap_or_else(|error| {
+                eprintln!("{}", error);
+                std::process::exit(1)
+            })
+        }
+        None => PieceSet::standard(),
+    };
+    // `tetris bot <mode>` watches the built-in bot play any single player
+    // mode, `tetris tbp <command> <mode>` an external Tetris Bot Protocol one
+    let bot = match args.next_if(|arg| arg == "bot" || arg == "tbp").as_deref() {
+        Some("bot") => Some(Driver::Heuristic(Bot::new())),
+        Some(_) => {
+            let command = args.next().expect("Usage: tetris tbp <bot command> [mode]");
+            let bot = ExternalBot::spawn(&command, Bot::DELAY).expect("Failed to start the bot");
+            Some(Driver::External(bot))
+        }
+        None => None,
+    };
+    let engine = move || Engine::with_pieces(pieces);
+    // a mode named on the command line is played straight away, and again
+    // on a retry; without one the game opens on the title screen
+    let first: Option<NewGame> = match args.next().as_deref() {
+        Some("sprint") => Some(Rc::new(move || Game::new(engine(), Sprint::new()))),
+        Some("ultra") => {
+            let limit = args
+                .next()
+                .map(|secs| secs.parse().expect("Ultra time limit must be in seconds"))
+                .map_or(Ultra::LIMIT, Duration::from_secs);
+            Some(Rc::new(move || {
+                Game::new(engine(), Ultra::with_limit(limit))
+            }))
+        }
+        Some(kind @ ("marathon" | "endless")) => {
+            let level = args.next().map_or(1, |level| {
+                level.parse().expect("Starting level must be a number")
+            });
+            let endless = kind == "endless";
+            Some(Rc::new(move || {
+                let marathon = match endless {
+                    false => Marathon::new(level),
+                    true => Marathon::endless(level),
+                };
+                Game::new(engine(), marathon)
+            }))
+        }
+        Some(kind @ ("dig" | "dig-endless")) => {
+            let rows = args.next().map_or(Dig::ROWS, |rows| {
+                rows.parse().expect("Garbage rows must be a number")
+            });
+            let endless = kind == "dig-endless";
+            Some(Rc::new(move || {
+                let dig = match endless {
+                    false => Dig::new(rows),
+                    true => Dig::endless(rows),
+                };
+                Game::new(engine(), dig)
+            }))
+        }
+        Some("fumen") => {
+            let text = args.next().expect("Usage: tetris fumen <fumen> [page]");
+            let page = args
+                .next()
+                .map_or(1, |page| page.parse().expect("Page must be a number"));
+            let engine = fumen::decode(&text)
+                .and_then(|pages| match pages.get(page.max(1) - 1) {
+                    Some(page) => Engine::from_page(page),
+                    None => Err(fumen::FumenError::new("there is no such page")),
+                })
+                .unwrap_or_else(|error| {
+                    eprintln!("{}", error);
+                    std::process::exit(1)
+                });
+            // a retry starts from the same page
+            Some(Rc::new(move || Game::new(engine.clone(), Practice)))
+        }
+        Some("bot-headless") => {
+            let pieces = args.next().map_or(1000, |pieces| {
+                pieces.parse().expect("Piece count must be a number")
+            });
+            return run_headless(pieces);
+        }
+        Some("versus") => return interface::run_versus(Versus::new(), &config),
+        Some("online") => {
+            let address = args
+                .next()
+                .unwrap_or_else(|| net::DEFAULT_ADDRESS.to_owned());
+            return interface::run_online(&address, &config);
+        }
+        Some("p2p") => {
+            let (bind, peer) = match (args.next(), args.next()) {
+                (Some(bind), Some(peer)) => (bind, peer),
+                _ => panic!("Usage: tetris p2p <bind address> <peer address> [latency ms] [loss]"),
+            };
+            let link = UdpLink::new(bind, peer).expect("Failed to open UDP socket");
+            let latency = args.next().map_or(Duration::ZERO, |millis| {
+                Duration::from_millis(millis.parse().expect("Latency must be in milliseconds"))
+            });
+            let loss = args.next().map_or(0.0, |loss| {
+                loss.parse().expect("Packet loss must be a fraction")
+            });
+            let link = Simulated::new(link, latency, loss, rand::random());
+            return interface::run_rollback(link, &config);
+        }
+        _ => None,
+    };
+    let modes = title_modes(pieces);
+    // records set with other pieces would not be comparable, so they are
+    // kept for the session only
+    let scores = match custom_pieces {
+        false => Scores::load_or_default(),
+        true => Scores::default(),
+    };
+    #[cfg(unix)]
+    if terminal {
+        return tetris::terminal::run(modes, first, bot, scores, &config);
+    }
+    interface::run(modes, first, bot, scores, &config)
+}
+
+// The single player modes on the title screen, with their usual settings
+fn title_modes(pieces: &'static PieceSet) -> Vec<(&'static str, NewGame)> {
+    let engine = move || Engine::with_pieces(pieces);
+    vec![
+        (
+            "Sprint",
+            Rc::new(move || Game::new(engine(), Sprint::new())),
+        ),
+        ("Ultra", Rc::new(move || Game::new(engine(), Ultra::new()))),
+        (
+            "Marathon",
+            Rc::new(move || Game::new(engine(), Marathon::new(1))),
+        ),
+        (
+            "Endless",
+            Rc::new(move || Game::new(engine(), Marathon::endless(1))),
+        ),
+        (
+            "Dig",
+            Rc::new(move || Game::new(engine(), Dig::new(Dig::ROWS))),
+        ),
+    ]
+}
+
+// Lets the bot play flat out without a window and reports how it did
+fn run_headless(pieces: u32) {
+    let mut engine = Engine::new();
+    let mut bot = Bot::with_weights(Default::default(), Duration::ZERO);
+    bot.play(&mut engine, pieces);
+
+    let score = engine.score();
+    println!("Pieces: {}", engine.pieces_placed());
+    println!("Lines: {}", engine.lines_cleared());
+    println!("Score: {}", score.points);
+    println!("Tetrises: {}", score.clears[3]);
+    if engine.is_topped_out() {
+        println!("Topped out");
     }
-    let mut engine = Engine::with_matrix(matrix);
-    engine.DEBUG_test_cursor_location(PieceKind::T, (5, 15).into());
-    interface::run(engine)
 }
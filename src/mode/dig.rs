@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use crate::engine::{garbage::Hole as GarbageHole, Engine, Matrix};
+
+use super::{format_time, pps, Mode, Outcome, Record, Stat};
+
+pub struct Dig {
+    rows: usize,
+    // keep the garbage topped up to `rows` instead of finishing once it is gone
+    endless: bool,
+    added: usize,
+}
+
+impl Dig {
+    pub const ROWS: usize = 10;
+
+    pub fn new(rows: usize) -> Self {
+        Self {
+            rows: rows.clamp(1, Matrix::HEIGHT / 2),
+            endless: false,
+            added: 0,
+        }
+    }
+
+    pub fn endless(height: usize) -> Self {
+        Self {
+            endless: true,
+            ..Self::new(height)
+        }
+    }
+
+    fn cleared(&self, engine: &Engine) -> usize {
+        self.added - engine.garbage_rows()
+    }
+}
+
+impl Mode for Dig {
+    // apart, so each has its own high score table
+    fn name(&self) -> &'static str {
+        match self.endless {
+            false => "Dig",
+            true => "Endless dig",
+        }
+    }
+
+    fn update(&mut self, engine: &mut Engine, _clock: Duration) {
+        let missing = if self.added == 0 || self.endless {
+            self.rows.saturating_sub(engine.garbage_rows())
+        } else {
+            0
+        };
+        if missing > 0 {
+            // a top out here is picked up by Game through the engine
+            let _ = engine.add_garbage(missing, GarbageHole::Messy);
+            self.added += missing;
+        }
+    }
+
+    fn outcome(&self, engine: &Engine, _clock: Duration) -> Option<Outcome> {
+        (!self.endless && engine.garbage_rows() == 0).then_some(Outcome::Completed)
+    }
+
+    fn stats(&self, engine: &Engine, clock: Duration) -> Vec<Stat> {
+        let garbage = if self.endless {
+            Stat::new("Dug", self.cleared(engine))
+        } else {
+            Stat::new("Garbage", engine.garbage_rows())
+        };
+        vec![
+            Stat::new("Time", format_time(clock)),
+            garbage,
+            Stat::new("Pieces", engine.pieces_placed()),
+        ]
+    }
+
+    fn results(&self, engine: &Engine, clock: Duration) -> Vec<Stat> {
+        vec![
+            Stat::new("Time", format_time(clock)),
+            Stat::new("Garbage cleared", self.cleared(engine)),
+            Stat::new("Pieces", engine.pieces_placed()),
+            Stat::new("PPS", format!("{:.2}", pps(engine, clock))),
+        ]
+    }
+
+    // the time to clear it all, or in endless how much was cleared
+    fn record(&self, engine: &Engine, clock: Duration, outcome: Outcome) -> Option<Record> {
+        match (self.endless, outcome) {
+            _ if self.rows != Self::ROWS => None,
+            (true, _) => Some(Record::Count(self.cleared(engine) as u32)),
+            (false, Outcome::Completed) => Some(Record::Time(clock)),
+            (false, Outcome::ToppedOut) => None,
+        }
+    }
+
+    fn progress(&self, engine: &Engine, _clock: Duration) -> f32 {
+        if self.endless {
+            engine.garbage_rows() as f32 / self.rows as f32
+        } else {
+            self.cleared(engine) as f32 / self.rows as f32
+        }
+    }
+}
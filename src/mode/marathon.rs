@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use crate::engine::Engine;
+
+use super::{format_time, Mode, Outcome, Record, Stat};
+
+pub struct Marathon {
+    start_level: u8,
+    // None runs until the player tops out
+    goal: Option<u32>,
+}
+
+impl Marathon {
+    pub const LINES: u32 = 150;
+    pub const LINES_PER_LEVEL: u32 = 10;
+
+    pub fn new(start_level: u8) -> Self {
+        Self {
+            start_level: start_level.clamp(1, Engine::MAX_LEVEL),
+            goal: Some(Self::LINES),
+        }
+    }
+
+    pub fn endless(start_level: u8) -> Self {
+        Self {
+            goal: None,
+            ..Self::new(start_level)
+        }
+    }
+
+    fn level(&self, engine: &Engine) -> u8 {
+        let gained = engine.lines_cleared() / Self::LINES_PER_LEVEL;
+        (self.start_level as u32 + gained).min(Engine::MAX_LEVEL as u32) as u8
+    }
+}
+
+impl Mode for Marathon {
+    fn name(&self) -> &'static str {
+        match self.goal {
+            Some(_) => "Marathon",
+            None => "Endless",
+        }
+    }
+
+    fn update(&mut self, engine: &mut Engine, _clock: Duration) {
+        engine.set_level(self.level(engine));
+    }
+
+    fn outcome(&self, engine: &Engine, _clock: Duration) -> Option<Outcome> {
+        let goal = self.goal?;
+        (engine.lines_cleared() >= goal).then_some(Outcome::Completed)
+    }
+
+    fn stats(&self, engine: &Engine, clock: Duration) -> Vec<Stat> {
+        let lines = match self.goal {
+            Some(goal) => format!("{}/{}", engine.lines_cleared(), goal),
+            None => engine.lines_cleared().to_string(),
+        };
+        vec![
+            Stat::new("Level", engine.level()),
+            Stat::new("Lines", lines),
+            Stat::new("Score", engine.score().points),
+            Stat::new("Time", format_time(clock)),
+        ]
+    }
+
+    fn results(&self, engine: &Engine, clock: Duration) -> Vec<Stat> {
+        let [singles, doubles, triples, tetrises] = engine.score().clears;
+        vec![
+            Stat::new("Score", engine.score().points),
+            Stat::new("Time", format_time(clock)),
+            Stat::new("Level", engine.level()),
+            Stat::new("Lines", engine.lines_cleared()),
+            Stat::new("Singles", singles),
+            Stat::new("Doubles", doubles),
+            Stat::new("Triples", triples),
+            Stat::new("Tetrises", tetrises),
+        ]
+    }
+
+    // any starting level, as starting higher is harder as well as worth more
+    fn record(&self, engine: &Engine, _clock: Duration, _outcome: Outcome) -> Option<Record> {
+        Some(Record::Count(engine.score().points))
+    }
+
+    fn progress(&self, engine: &Engine, _clock: Duration) -> f32 {
+        match self.goal {
+            Some(goal) => engine.lines_cleared() as f32 / goal as f32,
+            None => {
+                let into_level = engine.lines_cleared() % Self::LINES_PER_LEVEL;
+                into_level as f32 / Self::LINES_PER_LEVEL as f32
+            }
+        }
+    }
+}
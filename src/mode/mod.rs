@@ -0,0 +1,365 @@
+use std::{
+    fmt::{self, Display},
+    time::Duration,
+};
+
+use crate::engine::Engine;
+
+pub use dig::Dig;
+pub use marathon::Marathon;
+pub use practice::Practice;
+pub use sprint::Sprint;
+pub use ultra::Ultra;
+pub use versus::{Battle, Standing, Versus};
+
+mod dig;
+mod marathon;
+mod practice;
+mod sprint;
+mod ultra;
+mod versus;
+
+// The rules layered on top of an Engine: when the game ends and what gets reported
+pub trait Mode {
+    fn name(&self) -> &'static str;
+
+    // Lets the mode adjust the engine, e.g. its level, as play goes on
+    fn update(&mut self, _engine: &mut Engine, _clock: Duration) {}
+
+    // Checked whenever the engine may have changed; Some ends the game
+    fn outcome(&self, engine: &Engine, clock: Duration) -> Option<Outcome>;
+
+    // The game ends the instant the clock reaches this
+    fn time_limit(&self) -> Option<Duration> {
+        None
+    }
+
+    fn stats(&self, engine: &Engine, clock: Duration) -> Vec<Stat>;
+
+    // Summary for the end screen
+    fn results(&self, engine: &Engine, clock: Duration) -> Vec<Stat> {
+        self.stats(engine, clock)
+    }
+
+    // How far through the mode's goal the player is, from 0.0 to 1.0
+    fn progress(&self, engine: &Engine, clock: Duration) -> f32;
+
+    // What a finished game goes on the high score table with, if anything.
+    // Settings other than the usual ones are left off, as they would not
+    // compare fairly.
+    fn record(&self, _engine: &Engine, _clock: Duration, _outcome: Outcome) -> Option<Record> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Completed,
+    ToppedOut,
+}
+
+// What a game is ranked by
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Record {
+    // the fastest first
+    Time(Duration),
+    // the most first, such as points
+    Count(u32),
+}
+
+impl Record {
+    // Ranks strictly higher than `other`; ties go to whoever was first
+    pub fn beats(self, other: Record) -> bool {
+        match (self, other) {
+            (Record::Time(time), Record::Time(other)) => time < other,
+            (Record::Count(count), Record::Count(other)) => count > other,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Record::Time(time) => write!(f, "{}", format_time(*time)),
+            Record::Count(count) => write!(f, "{}", count),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stat {
+    pub label: &'static str,
+    pub value: String,
+}
+
+impl Stat {
+    pub fn new(label: &'static str, value: impl ToString) -> Self {
+        Self {
+            label,
+            value: value.to_string(),
+        }
+    }
+}
+
+pub struct Game {
+    engine: Engine,
+    mode: Box<dyn Mode>,
+    countdown: Duration,
+    clock: Duration,
+    outcome: Option<Outcome>,
+}
+
+impl Game {
+    pub const COUNTDOWN: Duration = Duration::from_secs(3);
+
+    pub fn new(mut engine: Engine, mut mode: impl Mode + 'static) -> Self {
+        mode.update(&mut engine, Duration::ZERO);
+        Self {
+            engine,
+            mode: Box::new(mode),
+            countdown: Self::COUNTDOWN,
+            clock: Duration::ZERO,
+            outcome: None,
+        }
+    }
+
+    pub fn update(&mut self, mut delta: Duration) {
+        if self.outcome.is_some() {
+            return;
+        }
+
+        if !self.countdown.is_zero() {
+            let spent = delta.min(self.countdown);
+            self.countdown -= spent;
+            delta -= spent;
+            if delta.is_zero() {
+                return;
+            }
+        }
+
+        if let Some(limit) = self.mode.time_limit() {
+            delta = delta.min(limit.saturating_sub(self.clock));
+        }
+        self.clock += delta;
+        // inputs since the last update may already have finished the game,
+        // in which case this frame still counts towards its time
+        self.check_outcome();
+        if self.outcome.is_some() {
+            return;
+        }
+        self.engine.update(delta);
+        self.check_outcome();
+    }
+
+    fn check_outcome(&mut self) {
+        if self.outcome.is_some() {
+            return;
+        }
+        self.mode.update(&mut self.engine, self.clock);
+        self.outcome = if self.engine.is_topped_out() {
+            Some(Outcome::ToppedOut)
+        } else {
+            self.mode.outcome(&self.engine, self.clock)
+        };
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    // Only hands out the engine while the game is accepting input
+    pub fn engine_mut(&mut self) -> Option<&mut Engine> {
+        if self.countdown.is_zero() && self.outcome.is_none() {
+            Some(&mut self.engine)
+        } else {
+            None
+        }
+    }
+
+    // Whole seconds left before play starts, rounded up
+    pub fn countdown(&self) -> Option<u64> {
+        if self.countdown.is_zero() {
+            None
+        } else {
+            Some(self.countdown.as_nanos().div_ceil(1_000_000_000) as u64)
+        }
+    }
+
+    pub fn clock(&self) -> Duration {
+        self.clock
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.mode.name()
+    }
+
+    // Finesse is judged the same in every mode, so it is added here
+    pub fn stats(&self) -> Vec<Stat> {
+        let mut stats = self.mode.stats(&self.engine, self.clock);
+        stats.push(Stat::new("Faults", self.engine.finesse().faults));
+        stats
+    }
+
+    pub fn results(&self) -> Vec<Stat> {
+        let finesse = self.engine.finesse();
+        let mut results = self.mode.results(&self.engine, self.clock);
+        results.push(Stat::new("Faults", finesse.faults));
+        results.push(Stat::new(
+            "Finesse",
+            format!("{:.1}%", finesse.accuracy() * 100.0),
+        ));
+        results
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.mode.progress(&self.engine, self.clock).clamp(0.0, 1.0)
+    }
+
+    // Once the game is over, what it goes on the high score table with
+    pub fn record(&self) -> Option<Record> {
+        self.mode.record(&self.engine, self.clock, self.outcome?)
+    }
+}
+
+pub fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+// Pieces per second over the given stretch of play
+pub fn pps(engine: &Engine, clock: Duration) -> f32 {
+    if clock.is_zero() {
+        0.0
+    } else {
+        engine.pieces_placed() as f32 / clock.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bot::Bot;
+
+    #[test]
+    fn countdown_holds_clock() {
+        let mut game = Game::new(Engine::new(), Sprint::new());
+        assert_eq!(game.countdown(), Some(3));
+        assert!(game.engine_mut().is_none());
+
+        game.update(Duration::from_millis(2500));
+        assert_eq!(game.countdown(), Some(1));
+        assert_eq!(game.clock(), Duration::ZERO);
+
+        game.update(Duration::from_millis(750));
+        assert_eq!(game.countdown(), None);
+        assert_eq!(game.clock(), Duration::from_millis(250));
+        assert!(game.engine_mut().is_some());
+    }
+
+    #[test]
+    fn time_limit_is_exact() {
+        let limit = Duration::from_secs(10);
+        let mut game = Game::new(Engine::new(), Ultra::with_limit(limit));
+        game.update(Game::COUNTDOWN + Duration::from_millis(9990));
+        assert_eq!(game.outcome(), None);
+
+        game.update(Duration::from_millis(33));
+        assert_eq!(game.clock(), limit);
+        assert_eq!(game.outcome(), Some(Outcome::Completed));
+    }
+
+    #[test]
+    fn sprint_time_counts_the_last_frame() {
+        let frame = Duration::from_millis(100);
+        let bot = Bot::new();
+        let mut game = Game::new(Engine::with_seed(1), Sprint::with_lines(1));
+        game.update(Game::COUNTDOWN);
+        let mut frames = 0;
+        while game.outcome().is_none() {
+            game.update(frame);
+            frames += 1;
+            if let Some(engine) = game.engine_mut() {
+                for input in bot.best_placement(engine) {
+                    engine.input(input);
+                }
+            }
+        }
+        assert_eq!(game.outcome(), Some(Outcome::Completed));
+        assert_eq!(game.clock(), frame * frames);
+    }
+
+    #[test]
+    fn marathon_starts_at_chosen_level() {
+        let game = Game::new(Engine::new(), Marathon::new(5));
+        assert_eq!(game.engine().level(), 5);
+
+        let game = Game::new(Engine::new(), Marathon::endless(99));
+        assert_eq!(game.engine().level(), Engine::MAX_LEVEL);
+    }
+
+    #[test]
+    fn dig_prefills_garbage() {
+        let mut game = Game::new(Engine::new(), Dig::new(4));
+        assert_eq!(game.engine().garbage_rows(), 4);
+
+        game.update(Game::COUNTDOWN + Duration::from_millis(100));
+        assert_eq!(game.engine().garbage_rows(), 4);
+        assert_eq!(game.outcome(), None);
+    }
+
+    // Lets the bot place pieces until `done`, or the game is over
+    fn dig_with_bot(game: &mut Game, done: impl Fn(&Game) -> bool) {
+        let bot = Bot::new();
+        game.update(Game::COUNTDOWN);
+        for _ in 0..100 {
+            game.update(Duration::from_millis(1));
+            if done(game) {
+                return;
+            }
+            let engine = match game.engine_mut() {
+                Some(engine) => engine,
+                None => return,
+            };
+            for input in bot.best_placement(engine) {
+                engine.input(input);
+            }
+        }
+    }
+
+    #[test]
+    fn dig_finishes_once_garbage_is_gone() {
+        let mut game = Game::new(Engine::with_seed(1), Dig::new(2));
+        dig_with_bot(&mut game, |_| false);
+        assert_eq!(game.engine().garbage_rows(), 0);
+        assert_eq!(game.outcome(), Some(Outcome::Completed));
+    }
+
+    #[test]
+    fn endless_dig_tops_up_garbage() {
+        let mut game = Game::new(Engine::with_seed(1), Dig::endless(2));
+        let dug = |game: &Game| {
+            let stats = game.stats();
+            let dug = stats.iter().find(|stat| stat.label == "Dug").unwrap();
+            dug.value.parse::<usize>().unwrap()
+        };
+        dig_with_bot(&mut game, |game| dug(game) >= 3);
+        assert!(dug(&game) >= 3);
+        assert_eq!(game.engine().garbage_rows(), 2);
+        assert_eq!(game.outcome(), None);
+    }
+
+    #[test]
+    fn time_format() {
+        assert_eq!(format_time(Duration::from_millis(83_456)), "1:23.456");
+    }
+}
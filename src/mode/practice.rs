@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use crate::engine::Engine;
+
+use super::{format_time, pps, Mode, Outcome, Stat};
+
+// No goal and no speed up, for working on a setup until the player stops
+pub struct Practice;
+
+impl Mode for Practice {
+    fn name(&self) -> &'static str {
+        "Practice"
+    }
+
+    fn outcome(&self, _engine: &Engine, _clock: Duration) -> Option<Outcome> {
+        None
+    }
+
+    fn stats(&self, engine: &Engine, clock: Duration) -> Vec<Stat> {
+        vec![
+            Stat::new("Time", format_time(clock)),
+            Stat::new("Lines", engine.lines_cleared()),
+            Stat::new("Pieces", engine.pieces_placed()),
+            Stat::new("PPS", format!("{:.2}", pps(engine, clock))),
+        ]
+    }
+
+    fn progress(&self, _engine: &Engine, _clock: Duration) -> f32 {
+        0.0
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use crate::engine::Engine;
+
+use super::{format_time, pps, Mode, Outcome, Record, Stat};
+
+pub struct Sprint {
+    lines: u32,
+}
+
+impl Sprint {
+    pub const LINES: u32 = 40;
+
+    pub fn new() -> Self {
+        Self::with_lines(Self::LINES)
+    }
+
+    pub fn with_lines(lines: u32) -> Self {
+        Self { lines }
+    }
+
+    fn lines_remaining(&self, engine: &Engine) -> u32 {
+        self.lines.saturating_sub(engine.lines_cleared())
+    }
+}
+
+impl Default for Sprint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mode for Sprint {
+    fn name(&self) -> &'static str {
+        "Sprint"
+    }
+
+    fn outcome(&self, engine: &Engine, _clock: Duration) -> Option<Outcome> {
+        (self.lines_remaining(engine) == 0).then_some(Outcome::Completed)
+    }
+
+    fn stats(&self, engine: &Engine, clock: Duration) -> Vec<Stat> {
+        vec![
+            Stat::new("Time", format_time(clock)),
+            Stat::new("Lines", self.lines_remaining(engine)),
+            Stat::new("PPS", format!("{:.2}", pps(engine, clock))),
+        ]
+    }
+
+    fn record(&self, _engine: &Engine, clock: Duration, outcome: Outcome) -> Option<Record> {
+        (outcome == Outcome::Completed && self.lines == Self::LINES).then_some(Record::Time(clock))
+    }
+
+    fn progress(&self, engine: &Engine, _clock: Duration) -> f32 {
+        engine.lines_cleared() as f32 / self.lines as f32
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use crate::engine::Engine;
+
+use super::{format_time, pps, Mode, Outcome, Record, Stat};
+
+pub struct Ultra {
+    limit: Duration,
+}
+
+impl Ultra {
+    pub const LIMIT: Duration = Duration::from_secs(120);
+
+    pub fn new() -> Self {
+        Self::with_limit(Self::LIMIT)
+    }
+
+    pub fn with_limit(limit: Duration) -> Self {
+        Self { limit }
+    }
+}
+
+impl Default for Ultra {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mode for Ultra {
+    fn name(&self) -> &'static str {
+        "Ultra"
+    }
+
+    fn outcome(&self, _engine: &Engine, clock: Duration) -> Option<Outcome> {
+        (clock >= self.limit).then_some(Outcome::Completed)
+    }
+
+    fn time_limit(&self) -> Option<Duration> {
+        Some(self.limit)
+    }
+
+    fn stats(&self, engine: &Engine, clock: Duration) -> Vec<Stat> {
+        vec![
+            Stat::new("Time", format_time(self.limit.saturating_sub(clock))),
+            Stat::new("Score", engine.score().points),
+            Stat::new("Lines", engine.lines_cleared()),
+        ]
+    }
+
+    fn results(&self, engine: &Engine, clock: Duration) -> Vec<Stat> {
+        let score = engine.score();
+        let minutes = clock.as_secs_f32() / 60.0;
+        let apm = if minutes > 0.0 {
+            score.attack as f32 / minutes
+        } else {
+            0.0
+        };
+        vec![
+            Stat::new("Score", score.points),
+            Stat::new("Lines", engine.lines_cleared()),
+            Stat::new("Attack", score.attack),
+            Stat::new("APM", format!("{:.1}", apm)),
+            Stat::new("Tetrises", score.clears[3]),
+            Stat::new("Max combo", score.max_combo),
+            Stat::new("PPS", format!("{:.2}", pps(engine, clock))),
+        ]
+    }
+
+    // topping out early still keeps the points
+    fn record(&self, engine: &Engine, _clock: Duration, _outcome: Outcome) -> Option<Record> {
+        (self.limit == Self::LIMIT).then_some(Record::Count(engine.score().points))
+    }
+
+    fn progress(&self, _engine: &Engine, clock: Duration) -> f32 {
+        clock.as_secs_f32() / self.limit.as_secs_f32()
+    }
+}
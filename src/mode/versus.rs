@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use crate::engine::Engine;
+
+use super::{Game, Mode, Outcome, Stat};
+
+// Each player's side of a versus match: play until topping out
+pub struct Battle;
+
+impl Mode for Battle {
+    fn name(&self) -> &'static str {
+        "Versus"
+    }
+
+    fn outcome(&self, _engine: &Engine, _clock: Duration) -> Option<Outcome> {
+        None
+    }
+
+    fn stats(&self, engine: &Engine, _clock: Duration) -> Vec<Stat> {
+        vec![
+            Stat::new("Attack", engine.score().attack),
+            Stat::new("Lines", engine.lines_cleared()),
+            Stat::new("Incoming", engine.pending_garbage()),
+        ]
+    }
+
+    fn progress(&self, engine: &Engine, _clock: Duration) -> f32 {
+        engine.pending_garbage() as f32 / Versus::DANGER_GARBAGE as f32
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Standing {
+    Playing,
+    Won(usize),
+    Draw,
+}
+
+pub struct Versus {
+    players: [Game; 2],
+    standing: Standing,
+}
+
+impl Versus {
+    // Incoming garbage at which a player's meter is full
+    const DANGER_GARBAGE: u32 = 10;
+
+    pub fn new() -> Self {
+        Self::with_engines(Engine::new(), Engine::new())
+    }
+
+    pub fn with_engines(first: Engine, second: Engine) -> Self {
+        Self {
+            players: [Game::new(first, Battle), Game::new(second, Battle)],
+            standing: Standing::Playing,
+        }
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        if self.standing != Standing::Playing {
+            return;
+        }
+
+        for player in &mut self.players {
+            player.update(delta);
+        }
+
+        let attacks = self
+            .players
+            .each_mut()
+            .map(|player| player.engine_mut().map_or(0, |engine| engine.take_attack()));
+        for (player, attack) in self.players.iter_mut().rev().zip(attacks) {
+            if let Some(engine) = player.engine_mut() {
+                engine.receive_garbage(attack);
+            }
+        }
+
+        let [first, second] = self
+            .players
+            .each_ref()
+            .map(|player| player.outcome().is_some());
+        self.standing = match (first, second) {
+            (false, false) => Standing::Playing,
+            (true, true) => Standing::Draw,
+            (true, false) => Standing::Won(1),
+            (false, true) => Standing::Won(0),
+        };
+    }
+
+    pub fn player(&self, index: usize) -> &Game {
+        &self.players[index]
+    }
+
+    pub fn player_mut(&mut self, index: usize) -> &mut Game {
+        &mut self.players[index]
+    }
+
+    pub fn players(&self) -> &[Game; 2] {
+        &self.players
+    }
+
+    pub fn standing(&self) -> Standing {
+        self.standing
+    }
+
+    // Both players share one countdown since they are updated in lockstep
+    pub fn countdown(&self) -> Option<u64> {
+        self.players[0].countdown()
+    }
+}
+
+impl Default for Versus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn topping_out_loses() {
+        let mut versus = Versus::new();
+        versus.update(Game::COUNTDOWN + Duration::from_millis(10));
+        assert_eq!(versus.standing(), Standing::Playing);
+
+        let loser = versus.player_mut(1).engine_mut().unwrap();
+        loser.receive_garbage(40);
+        loser.hard_drop();
+        versus.update(Duration::from_millis(10));
+        assert_eq!(versus.standing(), Standing::Won(0));
+    }
+}
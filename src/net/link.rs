@@ -0,0 +1,127 @@
+use std::{
+    collections::VecDeque,
+    io::{self, ErrorKind},
+    net::{ToSocketAddrs, UdpSocket},
+    sync::mpsc::{channel, Receiver, Sender},
+    time::{Duration, Instant},
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// An unreliable datagram link to the other peer: packets may be lost or late
+pub trait Link {
+    fn send(&mut self, packet: &[u8]);
+    fn recv(&mut self) -> Option<Vec<u8>>;
+}
+
+pub struct UdpLink {
+    socket: UdpSocket,
+}
+
+impl UdpLink {
+    pub fn new(bind: impl ToSocketAddrs, peer: impl ToSocketAddrs) -> io::Result<Self> {
+        let socket = UdpSocket::bind(bind)?;
+        socket.connect(peer)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+}
+
+impl Link for UdpLink {
+    fn send(&mut self, packet: &[u8]) {
+        // nobody listening yet shows up as an error; the packet is simply lost
+        let _ = self.socket.send(packet);
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        let mut buffer = [0; 1500];
+        loop {
+            match self.socket.recv(&mut buffer) {
+                Ok(length) => return Some(buffer[..length].to_vec()),
+                Err(error) if error.kind() == ErrorKind::ConnectionRefused => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+// Both ends of an in-process link, for tests and local play
+pub struct ChannelLink {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+}
+
+impl ChannelLink {
+    pub fn pair() -> (Self, Self) {
+        let (first_sender, second_receiver) = channel();
+        let (second_sender, first_receiver) = channel();
+        (
+            Self {
+                sender: first_sender,
+                receiver: first_receiver,
+            },
+            Self {
+                sender: second_sender,
+                receiver: second_receiver,
+            },
+        )
+    }
+}
+
+impl Link for ChannelLink {
+    fn send(&mut self, packet: &[u8]) {
+        let _ = self.sender.send(packet.to_vec());
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+// Wraps a link, delaying every outgoing packet by `latency` and dropping a
+// `loss` fraction of them
+pub struct Simulated<L> {
+    link: L,
+    latency: Duration,
+    loss: f64,
+    rng: StdRng,
+    delayed: VecDeque<(Instant, Vec<u8>)>,
+}
+
+impl<L: Link> Simulated<L> {
+    pub fn new(link: L, latency: Duration, loss: f64, seed: u64) -> Self {
+        Self {
+            link,
+            latency,
+            loss: loss.clamp(0.0, 1.0),
+            rng: StdRng::seed_from_u64(seed),
+            delayed: VecDeque::new(),
+        }
+    }
+
+    fn flush(&mut self) {
+        let now = Instant::now();
+        while let Some((due, _)) = self.delayed.front() {
+            if *due > now {
+                break;
+            }
+            let (_, packet) = self.delayed.pop_front().unwrap();
+            self.link.send(&packet);
+        }
+    }
+}
+
+impl<L: Link> Link for Simulated<L> {
+    fn send(&mut self, packet: &[u8]) {
+        if !self.rng.gen_bool(self.loss) {
+            self.delayed
+                .push_back((Instant::now() + self.latency, packet.to_vec()));
+        }
+        self.flush();
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        self.flush();
+        self.link.recv()
+    }
+}
@@ -0,0 +1,204 @@
+// Networked versus over TCP. Clients connect to a `tetris-server`, which
+// pairs them up and relays messages between the two; peer-to-peer play over
+// UDP, without a server, is in `rollback`. Every message is one line of
+// ASCII text, a keyword then its arguments separated by single spaces:
+//
+//   WAITING          server to client  connected, waiting for an opponent
+//   START            server to client  both players are in, begin the countdown
+//   ATTACK <lines>   both              garbage sent to the opponent
+//   BOARD <cells>    both              the sender's matrix, active piece in
+//   TOPOUT           client to server  the sender has lost
+//   END WIN|LOSE     server to client  the match is over
+//
+// `<cells>` is `Matrix::WIDTH * Matrix::HEIGHT` characters, bottom row
+// first, with `Color::letter` for filled cells and `.` for empty ones.
+
+use std::{
+    fmt::{self, Display},
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    net::TcpStream,
+    str::FromStr,
+};
+
+use crate::engine::{Color, Engine, Matrix};
+
+pub mod link;
+pub mod rollback;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:4000";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Waiting,
+    Start,
+    Attack(u32),
+    Board(String),
+    TopOut,
+    End { won: bool },
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::Waiting => write!(f, "WAITING"),
+            Message::Start => write!(f, "START"),
+            Message::Attack(lines) => write!(f, "ATTACK {}", lines),
+            Message::Board(cells) => write!(f, "BOARD {}", cells),
+            Message::TopOut => write!(f, "TOPOUT"),
+            Message::End { won: true } => write!(f, "END WIN"),
+            Message::End { won: false } => write!(f, "END LOSE"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError(String);
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Malformed message: {:?}", self.0)
+    }
+}
+
+impl FromStr for Message {
+    type Err = ParseError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let error = || ParseError(line.to_owned());
+        let (keyword, argument) = match line.split_once(' ') {
+            Some((keyword, argument)) => (keyword, Some(argument)),
+            None => (line, None),
+        };
+        Ok(match (keyword, argument) {
+            ("WAITING", None) => Message::Waiting,
+            ("START", None) => Message::Start,
+            ("ATTACK", Some(lines)) => Message::Attack(lines.parse().map_err(|_| error())?),
+            ("BOARD", Some(cells)) if cells.len() == Matrix::WIDTH * Matrix::HEIGHT => {
+                Message::Board(cells.to_owned())
+            }
+            ("TOPOUT", None) => Message::TopOut,
+            ("END", Some("WIN")) => Message::End { won: true },
+            ("END", Some("LOSE")) => Message::End { won: false },
+            _ => return Err(error()),
+        })
+    }
+}
+
+// Encodes the engine's matrix, with the active piece stamped in, as `BOARD` cells
+pub fn encode_board(engine: &Engine) -> String {
+    let mut cells: Vec<char> = engine
+        .cells()
+        .map(|(_, cell)| cell.map_or('.', Color::letter))
+        .collect();
+    if let Some((cursor, color)) = engine.cursor_info() {
+        for coord in cursor.into_iter().filter(|coord| coord.y < Matrix::HEIGHT) {
+            cells[coord.y * Matrix::WIDTH + coord.x] = color.letter();
+        }
+    }
+    cells.into_iter().collect()
+}
+
+pub fn decode_board(cells: &str) -> Option<Matrix> {
+    let mut matrix = Matrix::blank();
+    for (index, letter) in cells.chars().enumerate() {
+        let coord = (index % Matrix::WIDTH, index / Matrix::WIDTH);
+        if coord.1 >= Matrix::HEIGHT {
+            return None;
+        }
+        matrix.set(
+            coord.into(),
+            match letter {
+                '.' => None,
+                letter => Some(Color::from_letter(letter)?),
+            },
+        );
+    }
+    Some(matrix)
+}
+
+// One end of a connection, reading without blocking the game loop
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    line: String,
+}
+
+impl Connection {
+    pub fn connect(address: &str) -> io::Result<Self> {
+        Self::new(TcpStream::connect(address)?)
+    }
+
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            line: String::new(),
+        })
+    }
+
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        self.writer.set_nonblocking(false)?;
+        let result = writeln!(self.writer, "{}", message);
+        self.writer.set_nonblocking(true)?;
+        result
+    }
+
+    // Every complete message received so far; an error means the peer is gone
+    pub fn poll(&mut self) -> io::Result<Vec<Message>> {
+        let mut messages = Vec::new();
+        loop {
+            match self.reader.read_line(&mut self.line) {
+                // hand over what arrived before the peer left; the next poll reports it
+                Ok(0) if !messages.is_empty() => return Ok(messages),
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(_) if self.line.ends_with('\n') => {
+                    let message = self.line.trim_end().parse().map_err(|error: ParseError| {
+                        io::Error::new(ErrorKind::InvalidData, error.to_string())
+                    });
+                    self.line.clear();
+                    messages.push(message?);
+                }
+                Ok(_) => {}
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(messages),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let board = ".".repeat(Matrix::WIDTH * Matrix::HEIGHT);
+        for message in [
+            Message::Waiting,
+            Message::Start,
+            Message::Attack(4),
+            Message::Board(board),
+            Message::TopOut,
+            Message::End { won: false },
+        ] {
+            assert_eq!(message.to_string().parse(), Ok(message));
+        }
+        assert!("ATTACK lots".parse::<Message>().is_err());
+        assert!("BOARD ..".parse::<Message>().is_err());
+    }
+
+    #[test]
+    fn board_snapshot() {
+        let mut matrix = Matrix::blank();
+        matrix.set((1, 0).into(), Some(Color::Grey));
+        let engine = Engine::with_matrix(matrix);
+
+        let cells = encode_board(&engine);
+        assert_eq!(&cells[..3], ".X.");
+
+        let decoded = decode_board(&cells).unwrap();
+        assert!(decoded.cells().eq(engine.cells()));
+    }
+}
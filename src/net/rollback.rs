@@ -0,0 +1,466 @@
+// GGPO-style rollback for peer-to-peer versus. Both peers run the same
+// deterministic `Match` one fixed frame at a time. Local inputs apply
+// straight away, and the remote player's are predicted to be empty until
+// they arrive; if they turn out otherwise, the match goes back to the
+// snapshot from the start of that frame and plays up to the present again.
+// A peer that sends nothing for `Session::TIMEOUT` is taken to have left.
+//
+// Packets over the `Link`, with integers little endian:
+//
+//   Hello   0, nonce u64, heard u8
+//   Inputs  1, first frame u32, ack u32, count u8, then count inputs u8
+//
+// `Inputs` carries every local input the peer has not acknowledged, so a
+// lost packet is covered by the next, and `ack` is how many of the peer's
+// inputs have arrived.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    engine::{Engine, Input, MoveKind, RotateKind},
+    mode::Standing,
+};
+
+use super::link::Link;
+
+// Everything pressed by one player during one frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameInput(u8);
+
+impl FrameInput {
+    // The order presses within a single frame are applied in
+    const INPUTS: [Input; 7] = [
+        Input::Move(MoveKind::Left),
+        Input::Move(MoveKind::Right),
+        Input::Rotate(RotateKind::Clockwise),
+        Input::Rotate(RotateKind::CounterClockwise),
+        Input::SoftDrop,
+        Input::HardDrop,
+        Input::Hold,
+    ];
+
+    pub fn press(&mut self, input: Input) {
+        let bit = Self::INPUTS
+            .iter()
+            .position(|&known| known == input)
+            .unwrap();
+        self.0 |= 1 << bit;
+    }
+
+    pub fn inputs(self) -> impl Iterator<Item = Input> {
+        Self::INPUTS
+            .into_iter()
+            .enumerate()
+            .filter(move |(bit, _)| self.0 & 1 << bit != 0)
+            .map(|(_, input)| input)
+    }
+}
+
+// Two engines stepped in lockstep, exchanging garbage; all state a rollback restores
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    engines: [Engine; 2],
+    frame: u32,
+}
+
+impl Match {
+    pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+    pub const COUNTDOWN_FRAMES: u32 = 180;
+
+    // Both players are dealt the same pieces
+    pub fn new(seed: u64) -> Self {
+        Self {
+            engines: [Engine::with_seed(seed), Engine::with_seed(seed)],
+            frame: 0,
+        }
+    }
+
+    pub fn step(&mut self, inputs: [FrameInput; 2]) {
+        self.frame += 1;
+        if self.frame <= Self::COUNTDOWN_FRAMES || self.standing() != Standing::Playing {
+            return;
+        }
+
+        for (engine, input) in self.engines.iter_mut().zip(inputs) {
+            for input in input.inputs() {
+                engine.input(input);
+            }
+            engine.update(Self::FRAME);
+        }
+
+        let [first, second] = self.engines.each_mut().map(Engine::take_attack);
+        self.engines[0].receive_garbage(second);
+        self.engines[1].receive_garbage(first);
+    }
+
+    pub fn engines(&self) -> &[Engine; 2] {
+        &self.engines
+    }
+
+    pub fn standing(&self) -> Standing {
+        match self.engines.each_ref().map(Engine::is_topped_out) {
+            [false, false] => Standing::Playing,
+            [true, true] => Standing::Draw,
+            [true, false] => Standing::Won(1),
+            [false, true] => Standing::Won(0),
+        }
+    }
+
+    // Whole seconds left before play starts, rounded up
+    pub fn countdown(&self) -> Option<u64> {
+        let frames = Self::COUNTDOWN_FRAMES.checked_sub(self.frame)?;
+        (frames > 0).then(|| (frames as u64).div_ceil(60))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Packet {
+    Hello {
+        nonce: u64,
+        heard: bool,
+    },
+    Inputs {
+        start: u32,
+        ack: u32,
+        inputs: Vec<FrameInput>,
+    },
+}
+
+impl Packet {
+    const MAX_INPUTS: usize = u8::MAX as usize;
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            Packet::Hello { nonce, heard } => {
+                bytes.push(0);
+                bytes.extend(nonce.to_le_bytes());
+                bytes.push(*heard as u8);
+            }
+            Packet::Inputs { start, ack, inputs } => {
+                bytes.push(1);
+                bytes.extend(start.to_le_bytes());
+                bytes.extend(ack.to_le_bytes());
+                bytes.push(inputs.len() as u8);
+                bytes.extend(inputs.iter().map(|input| input.0));
+            }
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let u32_at = |at: usize| Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+        match bytes.first()? {
+            0 if bytes.len() == 10 => Some(Packet::Hello {
+                nonce: u64::from_le_bytes(bytes[1..9].try_into().ok()?),
+                heard: bytes[9] != 0,
+            }),
+            1 => {
+                let count = *bytes.get(9)? as usize;
+                let inputs = bytes.get(10..10 + count)?;
+                Some(Packet::Inputs {
+                    start: u32_at(1)?,
+                    ack: u32_at(5)?,
+                    inputs: inputs.iter().map(|&input| FrameInput(input)).collect(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+// Agrees on who is player one and on the match seed before a session starts
+pub struct Handshake {
+    nonce: u64,
+    peer: Option<u64>,
+}
+
+impl Handshake {
+    pub fn new(nonce: u64) -> Self {
+        Self { nonce, peer: None }
+    }
+
+    // Call once per frame until it hands back the session
+    pub fn poll(&mut self, link: &mut dyn Link) -> Option<Session> {
+        let mut started = false;
+        while let Some(packet) = link.recv().as_deref().and_then(Packet::decode) {
+            match packet {
+                Packet::Hello { nonce, heard } if nonce != self.nonce => {
+                    self.peer = Some(nonce);
+                    started |= heard;
+                }
+                // the peer only starts sending inputs after hearing us
+                Packet::Inputs { .. } => started = true,
+                Packet::Hello { .. } => {}
+            }
+        }
+
+        let hello = Packet::Hello {
+            nonce: self.nonce,
+            heard: self.peer.is_some(),
+        };
+        link.send(&hello.encode());
+
+        let peer = self.peer.filter(|_| started)?;
+        let local = (self.nonce > peer) as usize;
+        Some(Session::new(local, self.nonce ^ peer))
+    }
+}
+
+pub struct Session {
+    local: usize,
+    current: Match,
+    // next frame to simulate
+    frame: u32,
+    local_inputs: Vec<FrameInput>,
+    // the unbroken run of remote inputs received so far
+    remote_inputs: Vec<FrameInput>,
+    // how many local inputs the peer has acknowledged
+    acked: u32,
+    // state at the start of each frame from `snapshot_base` up to `frame`
+    snapshots: VecDeque<Match>,
+    snapshot_base: u32,
+    rollbacks: u32,
+    // when the last packet arrived from the peer
+    last_heard: Instant,
+}
+
+impl Session {
+    // How far ahead of the last confirmed remote input the local side may run
+    pub const MAX_PREDICTION: u32 = 8;
+    // How long the peer may go unheard before it is taken to have left
+    pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub fn new(local: usize, seed: u64) -> Self {
+        Self {
+            local,
+            current: Match::new(seed),
+            frame: 0,
+            local_inputs: Vec::new(),
+            remote_inputs: Vec::new(),
+            acked: 0,
+            snapshots: VecDeque::new(),
+            snapshot_base: 0,
+            rollbacks: 0,
+            last_heard: Instant::now(),
+        }
+    }
+
+    // Simulates the next frame, unless that would predict too far ahead
+    pub fn advance(&mut self, input: FrameInput) -> bool {
+        if self.frame - self.confirmed() >= Self::MAX_PREDICTION {
+            return false;
+        }
+        self.local_inputs.push(input);
+        self.snapshots.push_back(self.current.clone());
+        self.current.step(self.inputs_for(self.frame));
+        self.frame += 1;
+        true
+    }
+
+    pub fn packet(&self) -> Packet {
+        let start = self.acked;
+        let end = self
+            .local_inputs
+            .len()
+            .min(start as usize + Packet::MAX_INPUTS);
+        Packet::Inputs {
+            start,
+            ack: self.remote_inputs.len() as u32,
+            inputs: self.local_inputs[start as usize..end].to_vec(),
+        }
+    }
+
+    pub fn handle(&mut self, packet: Packet) {
+        self.last_heard = Instant::now();
+        let (start, ack, inputs) = match packet {
+            Packet::Inputs { start, ack, inputs } => (start, ack, inputs),
+            Packet::Hello { .. } => return,
+        };
+        self.acked = self.acked.max(ack.min(self.local_inputs.len() as u32));
+
+        let received = self.remote_inputs.len() as u32;
+        // a gap before these inputs is filled in by a later packet
+        if start > received {
+            return;
+        }
+
+        let mut mispredicted = None;
+        for &input in inputs.iter().skip((received - start) as usize) {
+            let frame = self.remote_inputs.len() as u32;
+            if frame < self.frame && input != FrameInput::default() && mispredicted.is_none() {
+                mispredicted = Some(frame);
+            }
+            self.remote_inputs.push(input);
+        }
+
+        if let Some(frame) = mispredicted {
+            self.rollback(frame);
+        }
+
+        while self.snapshot_base < self.confirmed() {
+            self.snapshots.pop_front();
+            self.snapshot_base += 1;
+        }
+    }
+
+    pub fn sync(&mut self, link: &mut dyn Link) {
+        while let Some(packet) = link.recv().as_deref().and_then(Packet::decode) {
+            self.handle(packet);
+        }
+        link.send(&self.packet().encode());
+    }
+
+    fn rollback(&mut self, from: u32) {
+        self.current = self.snapshots[(from - self.snapshot_base) as usize].clone();
+        for frame in from..self.frame {
+            self.snapshots[(frame - self.snapshot_base) as usize] = self.current.clone();
+            self.current.step(self.inputs_for(frame));
+        }
+        self.rollbacks += 1;
+    }
+
+    fn inputs_for(&self, frame: u32) -> [FrameInput; 2] {
+        let local = self.local_inputs[frame as usize];
+        // prediction: the remote player pressed nothing
+        let remote = self
+            .remote_inputs
+            .get(frame as usize)
+            .copied()
+            .unwrap_or_default();
+        if self.local == 0 {
+            [local, remote]
+        } else {
+            [remote, local]
+        }
+    }
+
+    // Frames up to which both players' inputs are known
+    pub fn confirmed(&self) -> u32 {
+        self.frame.min(self.remote_inputs.len() as u32)
+    }
+
+    // Whether the peer has gone quiet for too long, such as by closing
+    // the game or losing its connection
+    pub fn timed_out(&self) -> bool {
+        self.last_heard.elapsed() >= Self::TIMEOUT
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn local(&self) -> usize {
+        self.local
+    }
+
+    pub fn state(&self) -> &Match {
+        &self.current
+    }
+
+    pub fn rollbacks(&self) -> u32 {
+        self.rollbacks
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{thread, time::Duration};
+
+    use super::*;
+    use crate::net::link::{ChannelLink, Simulated};
+
+    #[test]
+    fn packets_round_trip() {
+        let inputs = Packet::Inputs {
+            start: 7,
+            ack: 3,
+            inputs: vec![FrameInput(0), FrameInput(0b100001)],
+        };
+        let hello = Packet::Hello {
+            nonce: 42,
+            heard: true,
+        };
+        for packet in [inputs, hello] {
+            assert_eq!(Packet::decode(&packet.encode()), Some(packet));
+        }
+        assert_eq!(Packet::decode(&[1, 0, 0]), None);
+    }
+
+    #[test]
+    fn peers_converge_over_lossy_link() {
+        const TARGET: u32 = Match::COUNTDOWN_FRAMES + 120;
+
+        let (first, second) = ChannelLink::pair();
+        let latency = Duration::from_millis(20);
+        let mut links = [
+            Simulated::new(first, latency, 0.25, 1),
+            Simulated::new(second, latency, 0.25, 2),
+        ];
+        let mut handshakes = [Handshake::new(11), Handshake::new(22)];
+        let mut sessions: [Option<Session>; 2] = [None, None];
+
+        for _ in 0..10_000 {
+            for (index, link) in links.iter_mut().enumerate() {
+                let session = match &mut sessions[index] {
+                    Some(session) => session,
+                    slot => {
+                        *slot = handshakes[index].poll(link);
+                        continue;
+                    }
+                };
+                session.sync(link);
+
+                let frame = session.frame();
+                let mut input = FrameInput::default();
+                if frame % (9 + index as u32 * 4) == 0 {
+                    input.press(Input::Move(MoveKind::Left));
+                }
+                if frame % 40 == 39 {
+                    input.press(Input::HardDrop);
+                }
+                if frame < TARGET {
+                    session.advance(input);
+                }
+            }
+
+            let done = sessions
+                .iter()
+                .all(|session| session.as_ref().is_some_and(|s| s.confirmed() == TARGET));
+            if done {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let [first, second] = sessions.map(Option::unwrap);
+        assert_eq!(first.confirmed(), TARGET);
+        assert_eq!(second.confirmed(), TARGET);
+        assert_ne!(first.local(), second.local());
+        assert!(first.rollbacks() + second.rollbacks() > 0);
+        assert_eq!(first.state(), second.state());
+        assert!(first.state().engines()[0].pieces_placed() > 0);
+    }
+
+    #[test]
+    fn silent_peer_times_out() {
+        let (mut link, _peer) = ChannelLink::pair();
+        let mut session = Session::new(0, 1);
+        session.sync(&mut link);
+        assert!(!session.timed_out());
+
+        session.last_heard = Instant::now() - Session::TIMEOUT;
+        session.sync(&mut link);
+        assert!(session.timed_out());
+
+        session.handle(Packet::Inputs {
+            start: 0,
+            ack: 0,
+            inputs: Vec::new(),
+        });
+        assert!(!session.timed_out());
+    }
+}
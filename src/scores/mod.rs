@@ -0,0 +1,216 @@
+// Each mode's best games, so they outlast the window. They live in
+// `tetris/scores.toml` in the XDG data directory, a table per mode name of
+// the top `TABLE_SIZE` by the mode's own measure, such as the fastest
+// Sprints or the highest Marathon scores. Games the bot had a hand in are
+// left off.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::xdg_dir, mode::Record};
+
+pub const TABLE_SIZE: usize = 10;
+pub const NAME_LENGTH: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub record: Record,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scores {
+    // by mode name, best first
+    tables: BTreeMap<String, Vec<Entry>>,
+    // where they are saved to, if anywhere
+    path: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoresError(String);
+
+impl Display for ScoresError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid high scores: {}", self.0)
+    }
+}
+
+impl Scores {
+    pub fn path() -> Option<PathBuf> {
+        Some(xdg_dir("XDG_DATA_HOME", ".local/share")?.join("tetris/scores.toml"))
+    }
+
+    // The saved scores, or none yet if there is no file. A broken file is
+    // reported and left alone rather than saved over.
+    pub fn load_or_default() -> Self {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => return Self::default(),
+        };
+        if !path.exists() {
+            return Self {
+                path: Some(path),
+                ..Self::default()
+            };
+        }
+        Self::load(&path).unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            Self::default()
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScoresError> {
+        let path = path.as_ref();
+        let in_file = |error: String| ScoresError(format!("{}: {}", path.display(), error));
+        let text = fs::read_to_string(path).map_err(|error| in_file(error.to_string()))?;
+        let scores = Self::parse(&text).map_err(|ScoresError(error)| in_file(error))?;
+        Ok(Self {
+            path: Some(path.to_owned()),
+            ..scores
+        })
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ScoresError> {
+        let file: BTreeMap<String, Vec<RawEntry>> = toml::from_str(text)
+            .map_err(|error| ScoresError(error.to_string().trim_end().to_owned()))?;
+        let mut scores = Self::default();
+        for (mode, entries) in file {
+            for entry in entries {
+                let entry = entry.cook().ok_or_else(|| {
+                    ScoresError(format!("{}: an entry needs either time_ms or count", mode))
+                })?;
+                scores.insert(&mode, entry);
+            }
+        }
+        Ok(scores)
+    }
+
+    // Writes the tables out, if they have somewhere to go
+    pub fn save(&self) -> Result<(), ScoresError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let text = self.to_text()?;
+        path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, text))
+            .map_err(|error| ScoresError(format!("{}: {}", path.display(), error)))
+    }
+
+    fn to_text(&self) -> Result<String, ScoresError> {
+        let file: BTreeMap<_, Vec<_>> = self
+            .tables
+            .iter()
+            .map(|(mode, entries)| (mode, entries.iter().map(RawEntry::from).collect()))
+            .collect();
+        toml::to_string(&file).map_err(|error| ScoresError(error.to_string()))
+    }
+
+    // Best first
+    pub fn table(&self, mode: &str) -> &[Entry] {
+        self.tables.get(mode).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn modes(&self) -> impl Iterator<Item = &str> {
+        self.tables.keys().map(String::as_str)
+    }
+
+    // Where `record` would go in `mode`'s table, from 0, if it makes it in
+    pub fn rank(&self, mode: &str, record: Record) -> Option<usize> {
+        let table = self.table(mode);
+        let rank = table
+            .iter()
+            .position(|entry| record.beats(entry.record))
+            .unwrap_or(table.len());
+        (rank < TABLE_SIZE).then_some(rank)
+    }
+
+    // Puts `entry` in its place, pushing the last off a full table
+    pub fn insert(&mut self, mode: &str, entry: Entry) -> Option<usize> {
+        let rank = self.rank(mode, entry.record)?;
+        let table = self.tables.entry(mode.to_owned()).or_default();
+        table.insert(rank, entry);
+        table.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+}
+
+// As written in the file, with the time in whole milliseconds
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct RawEntry {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    time_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    count: Option<u32>,
+}
+
+impl RawEntry {
+    fn cook(self) -> Option<Entry> {
+        let record = match (self.time_ms, self.count) {
+            (Some(millis), None) => Record::Time(Duration::from_millis(millis)),
+            (None, Some(count)) => Record::Count(count),
+            _ => return None,
+        };
+        Some(Entry {
+            name: self.name,
+            record,
+        })
+    }
+}
+
+impl From<&Entry> for RawEntry {
+    fn from(entry: &Entry) -> Self {
+        let (time_ms, count) = match entry.record {
+            Record::Time(time) => (Some(time.as_millis() as u64), None),
+            Record::Count(count) => (None, Some(count)),
+        };
+        Self {
+            name: entry.name.clone(),
+            time_ms,
+            count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tables_keep_the_best() {
+        let mut scores = Scores::default();
+        let entry = |name: &str, secs| Entry {
+            name: name.to_owned(),
+            record: Record::Time(Duration::from_secs(secs)),
+        };
+        for secs in 50..50 + TABLE_SIZE as u64 {
+            assert!(scores.insert("Sprint", entry("AAA", secs)).is_some());
+        }
+        assert_eq!(
+            scores.rank("Sprint", Record::Time(Duration::from_secs(80))),
+            None
+        );
+        assert_eq!(scores.insert("Sprint", entry("BBB", 45)), Some(0));
+        // a tie goes after the record it matches
+        assert_eq!(scores.insert("Sprint", entry("CCC", 50)), Some(2));
+        assert_eq!(scores.table("Sprint").len(), TABLE_SIZE);
+        assert_eq!(scores.rank("Marathon", Record::Count(0)), Some(0));
+
+        let text = scores.to_text().unwrap();
+        assert!(text.contains("time_ms = 45000"));
+        assert_eq!(Scores::parse(&text).unwrap(), scores);
+
+        let error = Scores::parse("[[Sprint]]\nname = 'AAA'").unwrap_err();
+        assert!(error.to_string().contains("time_ms"));
+    }
+}
@@ -0,0 +1,418 @@
+// A frontend for terminals, for playing over SSH or without a display. Each
+// frame is laid out on a grid of character cells, two to a matrix cell, and
+// written out whole with ANSI colours. Keys are read in raw mode; terminals
+// only report presses, so a held key moves at the terminal's repeat rate
+// rather than with the configured handling.
+
+use std::time::Duration;
+
+use crate::{
+    bot::Driver,
+    config::{
+        theme::{Pattern, Theme},
+        Bindings, Config, Visuals,
+    },
+    engine::{piece::Kind as PieceKind, Color, Matrix},
+    frontend::{
+        self,
+        menu::{self, Menu},
+        Command, Focus, Frame, FrameClock, InputSource, NewGame, Renderer,
+    },
+    mode::{Game, Outcome},
+    scores::Scores,
+};
+
+use raw::RawTerminal;
+
+mod raw;
+
+const FRAME: Duration = Duration::from_millis(16);
+const WIDTH: usize = 64;
+const HEIGHT: usize = Matrix::HEIGHT + 4;
+const MATRIX_COLUMN: usize = 16;
+const QUEUE_COLUMN: usize = MATRIX_COLUMN + 2 * Matrix::WIDTH + 4;
+
+// Plays in the terminal with the keys and visuals in `config`, from the
+// title screen or straight into `first`, keeping records in `scores`;
+// Ctrl-C always quits
+pub fn run(
+    modes: Vec<(&'static str, NewGame)>,
+    first: Option<NewGame>,
+    bot: Option<Driver>,
+    scores: Scores,
+    config: &Config,
+) {
+    let terminal = RawTerminal::enable().expect("Failed to put the terminal in raw mode");
+    frontend::run(
+        modes,
+        first,
+        bot,
+        scores,
+        config.handling,
+        &mut Typing(&terminal, &config.keys),
+        &mut FrameClock::new(FRAME),
+        &mut Frames(&terminal, config.visuals.clone()),
+    );
+}
+
+struct Typing<'terminal>(&'terminal RawTerminal, &'terminal Bindings);
+
+impl InputSource for Typing<'_> {
+    fn poll(&mut self, focus: Focus) -> Vec<Command> {
+        parse_keys(&self.0.read().unwrap_or_default(), self.1, focus)
+    }
+}
+
+struct Frames<'terminal>(&'terminal RawTerminal, Visuals);
+
+// There is no clipboard to share to, and printing would spoil the frame
+impl Renderer for Frames<'_> {
+    fn draw(&mut self, frame: &Frame) -> Result<(), ()> {
+        let screen = layout(frame, &self.1);
+        self.0.write(&screen.render(&self.1.theme)).map_err(drop)
+    }
+
+    fn next_theme(&mut self) {
+        self.1.next_theme();
+    }
+}
+
+// Arrow keys arrive as escape sequences, everything else as single bytes.
+// With a menu up, the keys that move about it do so, and while a name is
+// being typed in the keys type it.
+fn parse_keys(bytes: &[u8], keys: &Bindings, focus: Focus) -> Vec<Command> {
+    let mut commands = Vec::new();
+    let mut rest = bytes;
+    while let Some(&byte) = rest.first() {
+        let (name, length) = match rest {
+            [0x1b, b'[' | b'O', arrow, ..] => {
+                let name = match arrow {
+                    b'A' => "Up",
+                    b'B' => "Down",
+                    b'C' => "Right",
+                    b'D' => "Left",
+                    _ => "",
+                };
+                (name.to_owned(), 3)
+            }
+            _ => {
+                let name = match byte {
+                    0x03 => {
+                        commands.push(Command::Quit);
+                        String::new()
+                    }
+                    0x1b => "Escape".to_owned(),
+                    b' ' => "Space".to_owned(),
+                    b'\r' => "Return".to_owned(),
+                    b'\t' => "Tab".to_owned(),
+                    0x7f => "Backspace".to_owned(),
+                    _ => (byte as char).to_string(),
+                };
+                (name, 1)
+            }
+        };
+        match focus {
+            Focus::Game => commands.extend(keys.command(&name)),
+            Focus::Menu => match menu::key_nav(&name) {
+                Some(nav) => commands.push(Command::Menu(nav)),
+                None => commands.extend(keys.command(&name)),
+            },
+            Focus::Text => commands.extend(menu::key_typed(&name)),
+        }
+        rest = &rest[length..];
+    }
+    commands
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Glyph {
+    Text(char),
+    // with its pattern's letter, if any
+    Block(Color, char),
+    // the ghost piece, drawn as outlines
+    Ghost(Color, char),
+}
+
+// A frame laid out before it is written, so nothing from the last one is
+// left behind
+struct Screen {
+    rows: Vec<Vec<Glyph>>,
+}
+
+impl Screen {
+    fn new() -> Self {
+        Self {
+            rows: vec![vec![Glyph::Text(' '); WIDTH]; HEIGHT],
+        }
+    }
+
+    fn text(&mut self, row: usize, column: usize, text: &str) {
+        let cells = self.rows[row].iter_mut().skip(column);
+        for (cell, letter) in cells.zip(text.chars()) {
+            *cell = Glyph::Text(letter);
+        }
+    }
+
+    // Matrix cells are two characters wide so they come out roughly square
+    fn cell(&mut self, row: usize, column: usize, color: Color, theme: &Theme) {
+        let letters = theme.pattern(color).map_or([' '; 2], Pattern::letters);
+        self.rows[row][column] = Glyph::Block(color, letters[0]);
+        self.rows[row][column + 1] = Glyph::Block(color, letters[1]);
+    }
+
+    fn ghost(&mut self, row: usize, column: usize, color: Color) {
+        self.rows[row][column] = Glyph::Ghost(color, '[');
+        self.rows[row][column + 1] = Glyph::Ghost(color, ']');
+    }
+
+    // The theme's background and panel colours are left to the terminal
+    fn render(&self, theme: &Theme) -> String {
+        let mut out = String::from("\x1b[H");
+        for (index, row) in self.rows.iter().enumerate() {
+            // no new line after the last row, which would scroll a full terminal
+            if index > 0 {
+                out += "\r\n";
+            }
+            let mut current = String::new();
+            for &glyph in row {
+                // background for blocks, foreground for ghosts
+                let (style, letter) = match glyph {
+                    Glyph::Text(letter) => ("\x1b[0m".to_owned(), letter),
+                    // patterns are written in the background colour
+                    Glyph::Block(color, letter) => {
+                        let (r, g, b) = theme.rgb(color);
+                        let (fr, fg, fb) = theme.background;
+                        let style =
+                            format!("\x1b[0;48;2;{};{};{};38;2;{};{};{}m", r, g, b, fr, fg, fb);
+                        (style, letter)
+                    }
+                    Glyph::Ghost(color, letter) => {
+                        let (r, g, b) = theme.rgb(color);
+                        (format!("\x1b[0;38;2;{};{};{}m", r, g, b), letter)
+                    }
+                };
+                if style != current {
+                    out += &style;
+                    current = style;
+                }
+                out.push(letter);
+            }
+            out += "\x1b[0m\x1b[K";
+        }
+        out
+    }
+}
+
+// The game, if there is one, with any menu over it
+fn layout(frame: &Frame, visuals: &Visuals) -> Screen {
+    let mut screen = Screen::new();
+    match frame.game {
+        Some(game) => draw_game(&mut screen, game, frame.driving, visuals),
+        None => screen.text(0, 0, "Tetris"),
+    }
+    if let Some(menu) = frame.menu {
+        draw_menu(&mut screen, menu);
+    }
+    screen
+}
+
+fn draw_game(screen: &mut Screen, game: &Game, driving: bool, visuals: &Visuals) {
+    let engine = game.engine();
+
+    let mut status = format!("Tetris - {}", game.name());
+    if let Some(seconds) = game.countdown() {
+        status += &format!(" | {}", seconds);
+    }
+    match game.outcome() {
+        Some(Outcome::Completed) => status += " | Complete!",
+        Some(Outcome::ToppedOut) => status += " | Game over",
+        None => {}
+    }
+    if driving {
+        status += " | Bot playing";
+    }
+    screen.text(0, 0, &status);
+
+    // the matrix, framed, with its top row on the third line
+    let top = 2;
+    let bottom = top + Matrix::HEIGHT;
+    let right = MATRIX_COLUMN + 2 * Matrix::WIDTH;
+    for row in top..bottom {
+        screen.text(row, MATRIX_COLUMN - 1, "│");
+        screen.text(row, right, "│");
+        for x in 0..Matrix::WIDTH {
+            screen.text(row, MATRIX_COLUMN + 2 * x, " .");
+        }
+    }
+    screen.text(bottom, MATRIX_COLUMN - 1, "└");
+    screen.text(bottom, MATRIX_COLUMN, &"─".repeat(2 * Matrix::WIDTH));
+    screen.text(bottom, right, "┘");
+
+    if let (true, Some(ghost), Some((_, color))) =
+        (visuals.ghost, engine.ghost_cells(), engine.cursor_info())
+    {
+        for coord in ghost.into_iter().filter(|coord| coord.y < Matrix::HEIGHT) {
+            screen.ghost(bottom - 1 - coord.y, MATRIX_COLUMN + 2 * coord.x, color);
+        }
+    }
+    let mut cells: Vec<_> = engine
+        .cells()
+        .filter_map(|(coord, cell)| Some((coord, cell?)))
+        .collect();
+    if let Some((cursor, color)) = engine.cursor_info() {
+        cells.extend(cursor.into_iter().map(|coord| (coord, color)));
+    }
+    for (coord, color) in cells {
+        if coord.y < Matrix::HEIGHT {
+            let column = MATRIX_COLUMN + 2 * coord.x;
+            screen.cell(bottom - 1 - coord.y, column, color, &visuals.theme);
+        }
+    }
+
+    screen.text(top, 1, "HOLD");
+    if let Some(kind) = engine.held() {
+        draw_piece(screen, top + 1, 1, kind, &visuals.theme);
+    }
+    let stats = match game.outcome() {
+        Some(_) => game.results(),
+        None => game.stats(),
+    };
+    for (row, stat) in (top + 7..bottom).zip(stats) {
+        // cut short rather than run into the matrix
+        let line = format!("{}: {}", stat.label, stat.value);
+        let line: String = line.chars().take(MATRIX_COLUMN - 2).collect();
+        screen.text(row, 0, &line);
+    }
+
+    screen.text(top, QUEUE_COLUMN, "NEXT");
+    let mut row = top + 1;
+    for kind in engine.queue().take(visuals.previews) {
+        let height = piece_rows(kind).len();
+        if row + height > bottom {
+            break;
+        }
+        draw_piece(screen, row, QUEUE_COLUMN, kind, &visuals.theme);
+        row += height + 1;
+    }
+}
+
+// Centred across the matrix, over whatever is there
+fn draw_menu(screen: &mut Screen, menu: &Menu) {
+    let width = 2 * Matrix::WIDTH;
+    let lines = menu.text();
+    let top = 2 + Matrix::HEIGHT.saturating_sub(lines.len()) / 2;
+    for (row, line) in (top..HEIGHT).zip(lines) {
+        let line: String = line.chars().take(width).collect();
+        screen.text(row, MATRIX_COLUMN, &format!("{:^1$}", line, width));
+    }
+}
+
+// The piece's cells in its bounding box, top row first
+fn piece_rows(kind: PieceKind) -> Vec<Vec<bool>> {
+    let cells = kind.cells();
+    let xs = || cells.iter().map(|cell| cell.x);
+    let ys = || cells.iter().map(|cell| cell.y);
+    let (min_x, max_x) = (xs().min().unwrap(), xs().max().unwrap());
+    let (min_y, max_y) = (ys().min().unwrap(), ys().max().unwrap());
+    (min_y..=max_y)
+        .rev()
+        .map(|y| {
+            (min_x..=max_x)
+                .map(|x| cells.iter().any(|cell| cell.x == x && cell.y == y))
+                .collect()
+        })
+        .collect()
+}
+
+fn draw_piece(screen: &mut Screen, row: usize, column: usize, kind: PieceKind, theme: &Theme) {
+    for (y, line) in piece_rows(kind).into_iter().enumerate() {
+        for (x, filled) in line.into_iter().enumerate() {
+            if filled {
+                screen.cell(row + y, column + 2 * x, kind.color(), theme);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        engine::{Engine, Input, MoveKind, RotateKind},
+        frontend::menu::Nav,
+        mode::Sprint,
+    };
+
+    #[test]
+    fn keys_and_arrows() {
+        let config = Config::parse("keys.hold = ['c', 'Tab']").unwrap();
+        let keys = parse_keys(b"\x1b[Dx\x1b[A?C\t\x1b\x03", &config.keys, Focus::Game);
+        assert_eq!(
+            keys,
+            [
+                Command::Input(Input::Move(MoveKind::Left)),
+                Command::Input(Input::Rotate(RotateKind::Clockwise)),
+                Command::Input(Input::HardDrop),
+                Command::Input(Input::Hold),
+                Command::Input(Input::Hold),
+                Command::Pause,
+                Command::Quit,
+            ]
+        );
+
+        let keys = parse_keys(b"\x1b[A\rq\x1b", &config.keys, Focus::Menu);
+        assert_eq!(
+            keys,
+            [
+                Command::Menu(Nav::Up),
+                Command::Menu(Nav::Select),
+                Command::Quit,
+                Command::Menu(Nav::Back),
+            ]
+        );
+
+        let keys = parse_keys(b"q1 \x7f\r", &config.keys, Focus::Text);
+        assert_eq!(
+            keys,
+            [
+                Command::Type('Q'),
+                Command::Type('1'),
+                Command::Type(' '),
+                Command::Erase,
+                Command::Menu(Nav::Select),
+            ]
+        );
+    }
+
+    fn playing(game: &Game) -> Frame<'_> {
+        Frame {
+            game: Some(game),
+            driving: false,
+            menu: None,
+        }
+    }
+
+    #[test]
+    fn frame_shows_the_stack() {
+        let engine = Engine::with_matrix("ZZ.......I".parse().unwrap());
+        let visuals = Config::default().visuals;
+        let game = Game::new(engine, Sprint::new());
+        let screen = layout(&playing(&game), &visuals);
+
+        let bottom = 2 + Matrix::HEIGHT - 1;
+        let row = &screen.rows[bottom][MATRIX_COLUMN..];
+        assert_eq!(row[..4], [Glyph::Block(Color::Red, ' '); 4]);
+        assert_eq!(row[4..6], [Glyph::Text(' '), Glyph::Text('.')]);
+        assert_eq!(row[18..20], [Glyph::Block(Color::Cyan, ' '); 2]);
+        assert!(screen.render(&visuals.theme).contains("Tetris - Sprint"));
+
+        let engine = Engine::with_matrix("ZZ.......I".parse().unwrap());
+        let visuals = Config::parse("visuals.theme = 'monochrome'")
+            .unwrap()
+            .visuals;
+        let game = Game::new(engine, Sprint::new());
+        let screen = layout(&playing(&game), &visuals);
+        let row = &screen.rows[bottom][MATRIX_COLUMN..];
+        assert_eq!(row[..2], [Glyph::Block(Color::Red, '+'); 2]);
+    }
+}